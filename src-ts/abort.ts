@@ -0,0 +1,14 @@
+import { invoke } from "./invoke.ts";
+
+/**
+ * Aborts in-flight requests carrying `tag` in `FetchOptions.tags`, including ones waiting on `Retry-After`.
+ * They reject with `aborted`.
+ * `sse`, `ndjson` and `grpcWebStream` subscriptions end with an `error` and a `close` message.
+ * Bodies opened by `fetchBegin` are released and further reads reject with `invalidResourceId`.
+ * Outbox requests with `tag` in `OutboxRequest.tags` are removed from the queue.
+ * `heartbeat` and `schedule` keep running; only the request in flight is aborted, so stop them with `close()`.
+ * Returns how many were aborted.
+ */
+export async function abortByTag(tag: string): Promise<number> {
+    return await invoke("cookie-fetch", "abort_by_tag", { tag }) as number;
+}
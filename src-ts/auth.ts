@@ -0,0 +1,38 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact } from "./cookieFetch.ts";
+
+export type AuthChallenge = {
+    status: 401 | 407;
+    url: string;
+    /** values of `WWW-Authenticate` or `Proxy-Authenticate` */
+    challenges: string[];
+};
+
+export type Credentials =
+    | { username: string; password?: string }
+    /** used as the `Authorization` (or `Proxy-Authorization`) header as is */
+    | { authorization: string };
+
+/**
+ * Handles challenges of requests sent with `authChallenge: true`, unless a Rust `AuthProvider` answered first.
+ * Returning nothing gives up and resolves the request with the 401/407 response.
+ * So does not answering within `auth_challenge.timeoutMs` of the plugin config (5 minutes by default).
+ */
+export async function onAuthChallenge(
+    handler: (
+        challenge: AuthChallenge,
+    ) => Credentials | void | Promise<Credentials | void>,
+): Promise<() => void> {
+    return await listen<AuthChallenge & { id: number }>(
+        "cookie-fetch://auth",
+        async (e) => {
+            const { id, ...challenge } = e.payload;
+            const reply = await handler(challenge) ?? {};
+            await invoke("cookie-fetch", "auth_reply", {
+                id,
+                reply: compact(reply),
+            });
+        },
+    );
+}
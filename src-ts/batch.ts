@@ -0,0 +1,29 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type Response } from "./cookieFetch.ts";
+
+export type BatchRequest = {
+    url: string;
+    /** `FormData` bodies are not converted in batches */
+    options?: FetchOptions;
+};
+
+export type BatchResult =
+    | { ok: Response }
+    | { error: { kind: string; message: string } };
+
+/**
+ * Sends all requests in one invoke, at most `concurrency` (default 6) at a time.
+ * Results are in the order of `requests`; a failed request does not fail the others.
+ */
+export async function fetchBatch(
+    requests: BatchRequest[],
+    concurrency?: number,
+): Promise<BatchResult[]> {
+    return await invoke("cookie-fetch", "fetch_batch", {
+        requests: requests.map(({ url, options }) => ({
+            url,
+            options: compact(options),
+        })),
+        concurrency,
+    }) as BatchResult[];
+}
@@ -0,0 +1,22 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type Response } from "./cookieFetch.ts";
+
+export type ChainStep = {
+    /** `{{name}}` is replaced with a value extracted by an earlier step */
+    url: string;
+    /** `{{name}}` in header values and utf-8 bodies is replaced as well; `FormData` is not converted */
+    options?: FetchOptions;
+    extract?: Record<string, { header: string } | { json: string }>;
+};
+
+/**
+ * Sends the steps in order with one cookie jar, without going back to the webview between them.
+ * Resolves to the response of every step; rejects at the first failed step or missing value.
+ */
+export async function fetchChain(steps: ChainStep[]): Promise<Response[]> {
+    return await invoke("cookie-fetch", "fetch_chain", {
+        steps: steps.map((step) =>
+            compact({ ...step, options: compact(step.options) })
+        ),
+    }) as Response[];
+}
@@ -0,0 +1,43 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type HeaderMap } from "./cookieFetch.ts";
+
+export type ProxyConfig = {
+    url: string;
+    /** url globs this proxy is used for; all urls when omitted */
+    targets?: string[];
+    username?: string;
+    password?: string;
+};
+
+/** omitted fields are left unchanged */
+export type ConfigPatch = {
+    /** replaces the configured proxies; an invalid one rejects with `invalidProxy` */
+    proxies?: ProxyConfig[];
+    /** used for each timeout a request does not set; a new `connectMs` is used once pooled clients are recreated */
+    timeouts?: NonNullable<FetchOptions["timeouts"]>;
+    /** added to requests that do not set a header of the same name */
+    defaultHeaders?: HeaderMap;
+    /** used when a request does not set `maxBodyBytes`; `null` removes the limit */
+    maxBodyBytes?: number | null;
+};
+
+export type EffectiveConfig = {
+    /** without credentials */
+    proxies: { url: string | null; targets: string[] }[];
+    timeouts: { connectMs: number | null; readMs: number | null; totalMs: number | null };
+    /** values of redacted headers are masked */
+    defaultHeaders: HeaderMap;
+    maxBodyBytes: number | null;
+};
+
+/**
+ * Requires `allow_set_config` in the plugin config.
+ * Changes apply to requests started afterwards; requests in flight keep their settings.
+ */
+export async function setConfig(patch: ConfigPatch): Promise<void> {
+    await invoke("cookie-fetch", "set_config", { patch: compact(patch) });
+}
+
+export async function getConfig(): Promise<EffectiveConfig> {
+    return await invoke("cookie-fetch", "get_config", {}) as EffectiveConfig;
+}
@@ -0,0 +1,12 @@
+import { invoke } from "./invoke.ts";
+
+/**
+ * With `consent.enabled` in the plugin config, the first request to a host outside the scope
+ * asks the user in a native dialog and the answer is remembered in the app data directory.
+ * The dialog needs the crate's `dialog` feature (on by default); without it such hosts stay blocked.
+ * Forgets the answer for `host`, or for every host when omitted, so the next request asks again.
+ * Returns whether anything was forgotten.
+ */
+export async function forgetConsent(host?: string): Promise<boolean> {
+    return await invoke("cookie-fetch", "forget_consent", { host }) as boolean;
+}
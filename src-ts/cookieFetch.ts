@@ -1,4 +1,5 @@
-import { invoke } from "https://raw.githubusercontent.com/maemon4095/tauri-plugin-bin-ipc/release/v0.3.0/src-ts/mod.ts";
+import { invoke } from "./invoke.ts";
+import { withFormData } from "./formData.ts";
 
 export type SameSite = "Strict" | "Lax" | "None";
 
@@ -11,41 +12,164 @@ export type CookieProps = {
     maxAge?: number;
     expires?: string;
     sameSite?: SameSite;
+    partitioned?: boolean;
+    partitionKey?: string;
 };
 
 export type Cookies = Record<string, Record<string, CookieProps>>;
 
 export type FetchOptions = {
+    /** any method token, including extension methods such as `PROPFIND` or `PURGE` */
     method?: string;
+    query?: Query;
+    /**
+     * fail with `urlNormalized` instead of sending when parsing would change the url's path or query,
+     * e.g. by encoding spaces or resolving `..`. Existing percent-encoding is always kept as written.
+     * This only detects the change: the http client sends parsed urls, so a url that needs
+     * raw characters or unresolved `..` segments cannot be sent and must be encoded by the caller.
+     */
+    exactUrl?: boolean;
+    /** fail with `idnRejected` instead of sending to an internationalized domain name, e.g. for user-entered urls */
+    rejectIdn?: boolean;
     headers?: HeaderMap;
     cookies?: Cookies;
     redirect?: RedirectPolicy;
-    body?: Uint8Array;
+    redirectGuard?: RedirectGuard;
+    body?: Body;
+    payloadType?: PayloadType;
+    /** with `payloadType: "text"`, fail with `invalidText` instead of replacing bad utf-8; `CookieFetchError.offset` is the byte offset */
+    strictDecoding?: boolean;
+    /**
+     * return response header values that are not visible ascii (bad utf-8, control characters) sanitized
+     * instead of failing with `invalidHeaderValue`. Bytes the http parser itself rejects still fail the request.
+     */
+    lenientHeaders?: boolean;
+    throwOnError?: boolean | { maxBodyLength?: number };
+    sniffContentType?: boolean;
+    ephemeralCookies?: Record<string, string>;
+    storeResponseCookies?: boolean;
+    /**
+     * the top-level site of the embedding page (scheme and registrable domain, e.g. `https://example.com`)
+     * that partitioned cookies belong to. It is not derived from the url, so passing a partitioned cookie
+     * in `cookies` without it fails with `partitionKeyRequired`.
+     */
+    partitionKey?: string;
+    /** on 401/407 ask `onAuthChallenge` for credentials and retry once */
+    authChallenge?: boolean;
+    /** sent as the `Host` header instead of the url's host */
+    hostOverride?: string;
+    /** connect to the url's host but present this name as the TLS SNI and `Host` */
+    sniOverride?: string;
+    /** used instead of the configured proxies; `"direct"` bypasses them */
+    proxy?: "direct" | { url: string; username?: string; password?: string };
+    /** when the network is unreachable, keep retrying for up to this long before failing */
+    waitForNetworkMs?: number;
+    timeouts?: {
+        /** from dns resolution to the end of the tls handshake; the request opens its own connection */
+        connectMs?: number;
+        /** between chunks of the response body */
+        readMs?: number;
+        /** from sending until the whole body has been read */
+        totalMs?: number;
+    };
+    /** on 429, or 503 with `Retry-After`, wait as the header says and resend, up to this long in total */
+    retryAfterMaxMs?: number;
+    /** channel obtained from `onProgress` */
+    progress?: string;
+    /** labels attached to logs, intercept events and `poolStatus` */
+    tags?: string[];
+    /** caps for this request, applied on top of the plugin config's `bandwidth`; multipart parts are throttled too */
+    bandwidth?: { uploadBytesPerSec?: number; downloadBytesPerSec?: number };
+    /** longer bodies are cut to this size and reported with `truncated` */
+    maxBodyBytes?: number;
 };
 
-export type RedirectPolicy = "follow" | "manual" | { limit: number };
+export type Query = Record<string, string | string[]> | [string, string][];
+export type Body =
+    | Uint8Array
+    | { base64: string }
+    /**
+     * streamed from disk without passing through the webview.
+     * `path` is typically obtained from the dialog API and must be allowed by the fs scope.
+     */
+    | { file: { path: string; contentType?: string } }
+    | { provider: string }
+    | { multipart: MultipartPart[] }
+    | { upload: number }
+    /** converted into multipart by the bindings */
+    | FormData;
+export type MultipartPart = {
+    name: string;
+    filename?: string;
+    contentType?: string;
+    headers?: HeaderMap;
+    body: Body;
+};
+export type PayloadType = "binary" | "text" | "base64";
+/** `"interactive"` asks the handler registered with `onRedirect` at every hop */
+export type RedirectPolicy = "follow" | "manual" | "interactive" | { limit: number };
+/** redirects refused by the guard fail with `redirectBlocked` carrying the target url */
+export type RedirectGuard = {
+    /** refuse redirects from https to http */
+    denyDowngrade?: boolean;
+    /** if not empty, refuse redirects to any other host */
+    allowedHosts?: string[];
+};
 export type HeaderMap = { [name: string]: string[] };
 
 export type Response = {
+    /** hosts are always sent in their ascii (punycode) form */
     url: string;
+    /** `url` with an internationalized host converted back to unicode, for display; null for ascii hosts */
+    unicodeUrl: string | null;
     status: number;
     headers: HeaderMap;
     cookies: Cookies;
-    body: Uint8Array;
+    body: Uint8Array | string;
+    suggestedFilename: string | null;
+    contentType: ContentType | null;
+    /** declared by the server */
+    contentLength: number | null;
+    /** read from the body, including what was cut off by `maxBodyBytes` */
+    receivedBytes: number;
+    truncated: boolean;
+    /** `ip:port` of the peer; the proxy's address when one was used */
+    remoteAddress: string | null;
+    /** `ip:port` this side of the connection used */
+    localAddress: string | null;
+    /** url of the configured or per-request proxy used, without credentials; env proxies are not known */
+    proxy: string | null;
+};
+
+export type ContentType = {
+    declared?: string;
+    sniffed?: string;
 };
 
+/**
+ * `data:` urls and asset urls from `convertFileSrc` are read locally and returned as a 200 response;
+ * assets must be allowed by the asset protocol scope, and a missing file is a 404 response.
+ * `maxBodyBytes` and `throwOnError` apply to them as to network responses.
+ * Rejects with `CookieFetchError`; with `throwOnError` its `status`, `headers` and `body` describe the response.
+ */
 export async function cookieFetch(
     url: string,
     options?: FetchOptions,
 ): Promise<Response> {
-    if (options !== undefined) {
-        const entries = Object.entries(options).filter(([, v]) =>
-            v !== undefined
-        );
-        options = Object.fromEntries(entries);
-    }
-    return await invoke("cookie-fetch", "fetch", {
-        url,
+    return await withFormData(
         options,
-    }) as Response;
+        async (options) =>
+            await invoke("cookie-fetch", "fetch", {
+                url,
+                options: compact(options),
+            }) as Response,
+    );
+}
+
+export function compact<T extends object>(options: T | undefined) {
+    if (options === undefined) {
+        return undefined;
+    }
+    const entries = Object.entries(options).filter(([, v]) => v !== undefined);
+    return Object.fromEntries(entries) as T;
 }
@@ -0,0 +1,27 @@
+import { invoke } from "./invoke.ts";
+
+export type CookieChange = "set" | "overwrite" | "delete" | "expire";
+
+/** cookie values are never recorded */
+export type JournalEntry = {
+    /** unix time in milliseconds */
+    timestamp: number;
+    /** `delete` for `Max-Age` of 0 or less, `expire` for an `Expires` in the past */
+    change: CookieChange;
+    domain: string;
+    path: string;
+    name: string;
+    /** url of the response that carried the `Set-Cookie` */
+    url: string;
+    /** `id` of the request's `LogEntry`; absent for requests that are not logged, such as subscriptions */
+    requestId?: number;
+};
+
+/**
+ * Newest first. Records jar changes made by `Set-Cookie` in responses.
+ * Cookies the jar rejects (e.g. for a foreign `Domain`) are not recorded.
+ * Empty unless `cookie_journal.maxEntries` is set in the plugin config.
+ */
+export async function cookieJournal(count: number): Promise<JournalEntry[]> {
+    return await invoke("cookie-fetch", "cookie_journal", { count }) as JournalEntry[];
+}
@@ -0,0 +1,111 @@
+import {
+    type Cookies,
+    type FetchOptions,
+    type HeaderMap,
+} from "./cookieFetch.ts";
+import { closeBody, fetchBegin, readChunk } from "./reader.ts";
+
+const CHUNK_SIZE = 64 * 1024;
+const NULL_BODY_STATUS = [101, 103, 204, 205, 304];
+
+/**
+ * A drop-in replacement of the WHATWG `fetch` routed through the plugin.
+ * Returns the native `Response` whose `body` streams from `read_chunk`.
+ * `options` accepts the plugin specific options such as `cookies`, and the cookies
+ * after the request are exposed as `response.cookies`.
+ */
+export async function fetch(
+    input: RequestInfo | URL,
+    init?: RequestInit,
+    options?: FetchOptions,
+): Promise<Response & { cookies: Cookies }> {
+    const request = new Request(input, init);
+    const signal = request.signal;
+    signal.throwIfAborted();
+
+    // FormData is streamed as multipart by the plugin, which sets its own boundary
+    const formData = init?.body instanceof FormData ? init.body : undefined;
+    const requestHeaders = toHeaderMap(request.headers);
+    if (formData !== undefined) {
+        delete requestHeaders["content-type"];
+    }
+
+    const body = formData ??
+        (request.body === null
+            ? undefined
+            : new Uint8Array(await request.arrayBuffer()));
+
+    const head = await fetchBegin(request.url, {
+        ...options,
+        method: request.method,
+        headers: { ...requestHeaders, ...options?.headers },
+        redirect: request.redirect === "follow"
+            ? options?.redirect
+            : "manual",
+        body: body ?? options?.body,
+    });
+
+    if (request.redirect === "error" && 300 <= head.status && head.status < 400) {
+        await closeBody(head.rid);
+        throw new TypeError(`unexpected redirect to ${head.headers["location"]?.[0]}`);
+    }
+
+    const onAbort = () => closeBody(head.rid);
+    signal.addEventListener("abort", onAbort, { once: true });
+
+    const stream = new ReadableStream<Uint8Array>({
+        async pull(controller) {
+            try {
+                signal.throwIfAborted();
+                const chunk = await readChunk(head.rid, CHUNK_SIZE);
+                if (chunk === null) {
+                    signal.removeEventListener("abort", onAbort);
+                    controller.close();
+                } else {
+                    controller.enqueue(chunk);
+                }
+            } catch (e) {
+                signal.removeEventListener("abort", onAbort);
+                controller.error(e);
+            }
+        },
+        async cancel() {
+            signal.removeEventListener("abort", onAbort);
+            await closeBody(head.rid);
+        },
+    });
+
+    const nullBody = NULL_BODY_STATUS.includes(head.status);
+    if (nullBody) {
+        await stream.cancel();
+    }
+
+    const headers = new Headers();
+    for (const [name, values] of Object.entries(head.headers)) {
+        for (const value of values) {
+            headers.append(name, value);
+        }
+    }
+
+    // the Response constructor rejects statuses outside 200..=599
+    const response = new Response(nullBody ? null : stream, {
+        status: 200 <= head.status && head.status <= 599 ? head.status : 200,
+        headers,
+    });
+    Object.defineProperties(response, {
+        status: { value: head.status },
+        url: { value: head.url },
+        redirected: { value: head.url !== request.url },
+        cookies: { value: head.cookies },
+    });
+
+    return response as Response & { cookies: Cookies };
+}
+
+function toHeaderMap(headers: Headers): HeaderMap {
+    const map: HeaderMap = {};
+    headers.forEach((value, name) => {
+        (map[name] ??= []).push(value);
+    });
+    return map;
+}
@@ -0,0 +1,60 @@
+import { invoke } from "./invoke.ts";
+import type { FetchOptions, MultipartPart } from "./cookieFetch.ts";
+
+type Upload = { id: number; blob: Blob };
+
+/**
+ * Converts a `FormData` body into a multipart body whose `File`/`Blob` entries are streamed
+ * through `upload_chunk` while `send` runs.
+ */
+export async function withFormData<T>(
+    options: FetchOptions | undefined,
+    send: (options: FetchOptions | undefined) => Promise<T>,
+): Promise<T> {
+    if (!(options?.body instanceof FormData)) {
+        return await send(options);
+    }
+
+    const uploads: Upload[] = [];
+    const parts: MultipartPart[] = [];
+    for (const [name, value] of options.body) {
+        if (typeof value === "string") {
+            parts.push({ name, body: new TextEncoder().encode(value) });
+            continue;
+        }
+        const id = await invoke("cookie-fetch", "upload_begin", {}) as number;
+        uploads.push({ id, blob: value });
+        parts.push({
+            name,
+            filename: value.name,
+            contentType: value.type || undefined,
+            body: { upload: id },
+        });
+    }
+
+    const response = send({ ...options, body: { multipart: parts } });
+    // parts are read in order, so the blobs must be pumped sequentially
+    const pump = (async () => {
+        for (const { id, blob } of uploads) {
+            const reader = blob.stream().getReader();
+            while (true) {
+                const { done, value } = await reader.read();
+                if (done) {
+                    break;
+                }
+                await invoke("cookie-fetch", "upload_chunk", { id, data: value });
+            }
+            await invoke("cookie-fetch", "upload_end", { id, cancel: false });
+        }
+    })();
+
+    try {
+        const [res] = await Promise.all([response, pump]);
+        return res;
+    } catch (e) {
+        for (const { id } of uploads) {
+            await invoke("cookie-fetch", "upload_end", { id, cancel: true });
+        }
+        throw e;
+    }
+}
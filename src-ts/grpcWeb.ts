@@ -0,0 +1,121 @@
+import { CookieFetchError, type ErrorReport, invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import {
+    type Body,
+    compact,
+    type Cookies,
+    type FetchOptions,
+    type HeaderMap,
+} from "./cookieFetch.ts";
+import type { Subscription } from "./sse.ts";
+
+export type GrpcWebMessage =
+    | { type: "head"; status: number; headers: HeaderMap; cookies: Cookies }
+    | { type: "message"; data: number[] }
+    /** `grpcStatus` and `grpcMessage` fall back to the headers for trailers-only responses */
+    | {
+        type: "trailers";
+        trailers: Record<string, string>;
+        grpcStatus?: number;
+        grpcMessage?: string;
+    }
+    | ({ type: "error" } & ErrorReport)
+    | { type: "close" };
+
+export type GrpcWebResponse = {
+    status: number;
+    headers: HeaderMap;
+    cookies: Cookies;
+    messages: Uint8Array[];
+    trailers: Record<string, string>;
+    grpcStatus?: number;
+    grpcMessage?: string;
+};
+
+/**
+ * Sends a unary or server-streaming call and delivers each message as it arrives.
+ * `maxBodyBytes`, `timeouts.readMs`, `bandwidth` and `lenientHeaders` of `options` apply to the response.
+ */
+export async function grpcWebStream(
+    url: string,
+    message: Body,
+    options: FetchOptions | undefined,
+    onMessage: (message: GrpcWebMessage) => void,
+): Promise<Subscription> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<GrpcWebMessage>(
+        `cookie-fetch://grpc-web/${channel}`,
+        (e) => {
+            onMessage(e.payload);
+            if (e.payload.type === "close") {
+                unlisten();
+            }
+        },
+    );
+
+    try {
+        await invoke("cookie-fetch", "grpc_web", {
+            url,
+            message,
+            options: compact(options),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async close() {
+            unlisten();
+            await invoke("cookie-fetch", "unsubscribe", { channel });
+        },
+    };
+}
+
+/** Collects every message of `grpcWebStream`; rejects with `CookieFetchError` if the call fails midway. */
+export async function grpcWeb(
+    url: string,
+    message: Body,
+    options?: FetchOptions,
+): Promise<GrpcWebResponse> {
+    return await new Promise((resolve, reject) => {
+        const messages: Uint8Array[] = [];
+        let head: Extract<GrpcWebMessage, { type: "head" }> | undefined;
+        let trailers: Extract<GrpcWebMessage, { type: "trailers" }> | undefined;
+        let error: CookieFetchError | undefined;
+
+        grpcWebStream(url, message, options, (m) => {
+            switch (m.type) {
+                case "head":
+                    head = m;
+                    break;
+                case "message":
+                    messages.push(Uint8Array.from(m.data));
+                    break;
+                case "trailers":
+                    trailers = m;
+                    break;
+                case "error": {
+                    const { type: _, ...report } = m;
+                    error = new CookieFetchError(report);
+                    break;
+                }
+                case "close":
+                    if (error !== undefined || head === undefined) {
+                        reject(error ?? new Error("grpc-web call closed without a response"));
+                        return;
+                    }
+                    resolve({
+                        status: head.status,
+                        headers: head.headers,
+                        cookies: head.cookies,
+                        messages,
+                        trailers: trailers?.trailers ?? {},
+                        grpcStatus: trailers?.grpcStatus,
+                        grpcMessage: trailers?.grpcMessage,
+                    });
+            }
+        }).catch(reject);
+    });
+}
@@ -0,0 +1,59 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type FetchOptions } from "./cookieFetch.ts";
+import type { Subscription } from "./sse.ts";
+
+export type HeartbeatMessage =
+    /** sent once when heartbeats that were succeeding start failing, e.g. after being logged out */
+    | { type: "failing"; status: number | null; message: string }
+    | { type: "recovered" }
+    /** sent when stopped by `abortByTag` with one of `options.tags` */
+    | { type: "close" };
+
+export type HeartbeatOptions = {
+    /** at least 1000 */
+    intervalMs: number;
+    /** any 2xx when omitted */
+    expectedStatus?: number;
+};
+
+/**
+ * Keeps requesting `url` every `intervalMs` with the same cookie jar,
+ * so cookies refreshed by the server are sent on the next beat.
+ */
+export async function heartbeat(
+    url: string,
+    heartbeatOptions: HeartbeatOptions,
+    options: Omit<FetchOptions, "body"> | undefined,
+    onMessage: (message: HeartbeatMessage) => void,
+): Promise<Subscription> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<HeartbeatMessage>(
+        `cookie-fetch://heartbeat/${channel}`,
+        (e) => {
+            onMessage(e.payload);
+            if (e.payload.type === "close") {
+                unlisten();
+            }
+        },
+    );
+
+    try {
+        await invoke("cookie-fetch", "heartbeat", {
+            url,
+            options: compact(options),
+            heartbeat: compact(heartbeatOptions),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async close() {
+            unlisten();
+            await invoke("cookie-fetch", "unsubscribe", { channel });
+        },
+    };
+}
@@ -0,0 +1,68 @@
+import type { CookieProps, Cookies, SameSite } from "./cookieFetch.ts";
+
+/**
+ * A cookie as exported by EditThisCookie / Cookie-Editor,
+ * or by Chrome DevTools' "Copy as JSON" (the DevTools protocol `Network.Cookie`).
+ */
+export type ExportedCookie = {
+    name: string;
+    value: string;
+    domain: string;
+    path?: string;
+    secure?: boolean;
+    httpOnly?: boolean;
+    /** EditThisCookie / Cookie-Editor: `"no_restriction" | "lax" | "strict" | "unspecified"`; DevTools: `"Strict" | "Lax" | "None"` */
+    sameSite?: string;
+    hostOnly?: boolean;
+    session?: boolean;
+    /** EditThisCookie / Cookie-Editor, in seconds since the epoch */
+    expirationDate?: number;
+    /** DevTools, in seconds since the epoch; `-1` for session cookies */
+    expires?: number;
+    /** DevTools; a string in older versions, `{ topLevelSite }` in newer ones */
+    partitionKey?: string | { topLevelSite: string };
+    partitioned?: boolean;
+};
+
+/** Converts exported cookies into `FetchOptions.cookies`. Accepts both formats mixed in one array. */
+export function importCookies(exported: ExportedCookie[]): Cookies {
+    const cookies: Cookies = {};
+    for (const c of exported) {
+        const domain = c.domain.replace(/^\./, "");
+        const hostOnly = c.hostOnly ?? !c.domain.startsWith(".");
+        const expires = c.expirationDate ?? c.expires;
+        const partitionKey = typeof c.partitionKey === "object"
+            ? c.partitionKey.topLevelSite
+            : c.partitionKey;
+
+        const props: CookieProps = {
+            value: c.value,
+            domain: hostOnly ? undefined : domain,
+            path: c.path,
+            secure: c.secure,
+            httpOnly: c.httpOnly,
+            sameSite: sameSite(c.sameSite),
+            expires: c.session || expires === undefined || expires < 0
+                ? undefined
+                : new Date(expires * 1000).toUTCString(),
+            partitioned: c.partitioned ?? (partitionKey !== undefined ? true : undefined),
+            partitionKey,
+        };
+        (cookies[domain] ??= {})[c.name] = props;
+    }
+    return cookies;
+}
+
+function sameSite(value: string | undefined): SameSite | undefined {
+    switch (value?.toLowerCase()) {
+        case "strict":
+            return "Strict";
+        case "lax":
+            return "Lax";
+        case "none":
+        case "no_restriction":
+            return "None";
+        default:
+            return undefined;
+    }
+}
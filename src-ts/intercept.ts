@@ -0,0 +1,39 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type HeaderMap } from "./cookieFetch.ts";
+
+export type InterceptedRequest = {
+    method: string;
+    url: string;
+    /** cookie values are redacted unless `redaction.revealCookies` is set */
+    headers: HeaderMap;
+    tags: string[];
+};
+
+export type InterceptReply = {
+    /** replaces headers of the same name */
+    headers?: HeaderMap;
+    cancel?: boolean;
+};
+
+/**
+ * Requires `intercept.enabled` in the plugin config.
+ * Requests are sent unmodified if no reply arrives within `intercept.timeoutMs`.
+ */
+export async function onIntercept(
+    handler: (
+        request: InterceptedRequest,
+    ) => InterceptReply | void | Promise<InterceptReply | void>,
+): Promise<() => void> {
+    return await listen<InterceptedRequest & { id: number }>(
+        "cookie-fetch://intercept",
+        async (e) => {
+            const { id, ...request } = e.payload;
+            const reply = await handler(request) ?? {};
+            await invoke("cookie-fetch", "intercept_reply", {
+                id,
+                reply: compact(reply),
+            });
+        },
+    );
+}
@@ -0,0 +1,65 @@
+import { invoke as binInvoke } from "https://raw.githubusercontent.com/maemon4095/tauri-plugin-bin-ipc/release/v0.3.0/src-ts/mod.ts";
+import type { HeaderMap } from "./cookieFetch.ts";
+
+/**
+ * Rejection value of every command of the plugin.
+ * `kind` is one of the error kinds named in the docs, e.g. `status`, `notAllowed` or `redirectBlocked`.
+ */
+export class CookieFetchError extends Error {
+    readonly kind: string;
+    /** for `status` and `rangeNotSatisfied` */
+    readonly status?: number;
+    /** for `status`; values of redacted headers are masked */
+    readonly headers?: HeaderMap;
+    /** for `status`, up to `throwOnError.maxBodyLength` */
+    readonly body?: Uint8Array;
+    /** for `invalidText`, the byte offset of the invalid sequence */
+    readonly offset?: number;
+
+    constructor(report: ErrorReport) {
+        super(report.message);
+        this.name = "CookieFetchError";
+        this.kind = report.kind;
+        this.status = report.status;
+        this.headers = report.headers;
+        this.body = report.body && Uint8Array.from(report.body);
+        this.offset = report.offset;
+    }
+}
+
+/** serialized form of `CookieFetchError`, also sent in `error` messages of subscriptions */
+export type ErrorReport = {
+    kind: string;
+    message: string;
+    status?: number;
+    headers?: HeaderMap;
+    body?: number[];
+    offset?: number;
+};
+
+function parseReport(e: unknown): ErrorReport | undefined {
+    const text = e instanceof Error ? e.message : typeof e === "string" ? e : undefined;
+    if (text === undefined) {
+        return undefined;
+    }
+    try {
+        const report = JSON.parse(text);
+        return typeof report?.kind === "string" ? report as ErrorReport : undefined;
+    } catch {
+        return undefined;
+    }
+}
+
+/** rejects with `CookieFetchError` for errors reported by the plugin */
+export async function invoke(
+    plugin: string,
+    command: string,
+    args: Parameters<typeof binInvoke>[2],
+): Promise<unknown> {
+    try {
+        return await binInvoke(plugin, command, args);
+    } catch (e) {
+        const report = parseReport(e);
+        throw report === undefined ? e : new CookieFetchError(report);
+    }
+}
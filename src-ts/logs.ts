@@ -0,0 +1,28 @@
+import { invoke } from "./invoke.ts";
+
+export type LogEntry = {
+    /** numbered from 1 on each start of the app */
+    id: number;
+    /** unix time in milliseconds */
+    timestamp: number;
+    method: string;
+    url: string;
+    status?: number;
+    durationMs: number;
+    requestSize?: number;
+    responseSize?: number;
+    error?: string;
+    /** recorded only for urls matching `log.verbose` */
+    requestHeaders?: Record<string, string[]>;
+    responseHeaders?: Record<string, string[]>;
+    responsePreview?: string;
+    tags?: string[];
+};
+
+/**
+ * Newest first. Empty unless `log.enabled` is set in the plugin config.
+ * Entries are written in the background, so a request that just finished may not be listed yet.
+ */
+export async function recentLogs(count: number): Promise<LogEntry[]> {
+    return await invoke("cookie-fetch", "recent_logs", { count }) as LogEntry[];
+}
@@ -1,10 +1,93 @@
 export {
     cookieFetch,
+    type Body,
+    type ContentType,
     type CookieProps,
     type Cookies,
     type FetchOptions,
     type HeaderMap,
+    type MultipartPart,
+    type PayloadType,
+    type Query,
     type RedirectPolicy,
     type Response,
     type SameSite,
 } from "./cookieFetch.ts";
+export {
+    sse,
+    type SseEvent,
+    type SseMessage,
+    type Subscription,
+} from "./sse.ts";
+export { ndjson, type NdjsonMessage } from "./ndjson.ts";
+export {
+    type WebSocketConnection,
+    wsConnect,
+    type WsMessage,
+} from "./ws.ts";
+export {
+    grpcWeb,
+    type GrpcWebMessage,
+    type GrpcWebResponse,
+    grpcWebStream,
+} from "./grpcWeb.ts";
+export {
+    closeBody,
+    fetchBegin,
+    readChunk,
+    type ResponseHead,
+} from "./reader.ts";
+export { type LogEntry, recentLogs } from "./logs.ts";
+export {
+    type InterceptedRequest,
+    type InterceptReply,
+    onIntercept,
+} from "./intercept.ts";
+export { fetch } from "./fetch.ts";
+export { installFetchShim, type ShimOptions } from "./shim.ts";
+export {
+    onRedirect,
+    type RedirectDecision,
+    type RedirectHop,
+} from "./redirect.ts";
+export {
+    type AuthChallenge,
+    type Credentials,
+    onAuthChallenge,
+} from "./auth.ts";
+export { isOffline, onNetworkStatus } from "./network.ts";
+export { preconnect } from "./preconnect.ts";
+export { prefetch, type PrefetchOptions } from "./prefetch.ts";
+export { fetchPreset } from "./preset.ts";
+export { type BatchRequest, type BatchResult, fetchBatch } from "./batch.ts";
+export { type ChainStep, fetchChain } from "./chain.ts";
+export { type OriginStats, poolStatus, type PoolStatus } from "./poolStatus.ts";
+export {
+    onProgress,
+    type ProgressEvent,
+    type ProgressListener,
+    type Transfer,
+} from "./progress.ts";
+export { abortByTag } from "./abort.ts";
+export { type ExportedCookie, importCookies } from "./importCookies.ts";
+export { type ByteRange, fetchRange, type RangeResponse } from "./range.ts";
+export { type Probe, probe } from "./probe.ts";
+export {
+    heartbeat,
+    type HeartbeatMessage,
+    type HeartbeatOptions,
+} from "./heartbeat.ts";
+export { schedule, type ScheduleOptions } from "./schedule.ts";
+export {
+    onOutboxEvent,
+    outboxEnqueue,
+    type OutboxEvent,
+    type OutboxItem,
+    outboxItems,
+    outboxRemove,
+    type OutboxRequest,
+} from "./outbox.ts";
+export { type CookieChange, cookieJournal, type JournalEntry } from "./cookieJournal.ts";
+export { forgetConsent } from "./consent.ts";
+export { type ConfigPatch, type EffectiveConfig, getConfig, type ProxyConfig, setConfig } from "./config.ts";
+export { CookieFetchError } from "./invoke.ts";
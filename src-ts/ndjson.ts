@@ -0,0 +1,48 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type FetchOptions } from "./cookieFetch.ts";
+import type { Subscription } from "./sse.ts";
+
+export type NdjsonMessage =
+    | { type: "value"; value: unknown }
+    /**
+     * `status` is set for a non-2xx response, which is not parsed; the message includes
+     * the start of the body when `throwOnError` is set.
+     */
+    | { type: "error"; message: string; status?: number }
+    | { type: "close" };
+
+export async function ndjson(
+    url: string,
+    options: FetchOptions | undefined,
+    onMessage: (message: NdjsonMessage) => void,
+): Promise<Subscription> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<NdjsonMessage>(
+        `cookie-fetch://ndjson/${channel}`,
+        (e) => {
+            onMessage(e.payload);
+            if (e.payload.type === "close") {
+                unlisten();
+            }
+        },
+    );
+
+    try {
+        await invoke("cookie-fetch", "ndjson", {
+            url,
+            options: compact(options),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async close() {
+            unlisten();
+            await invoke("cookie-fetch", "unsubscribe", { channel });
+        },
+    };
+}
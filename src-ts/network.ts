@@ -0,0 +1,20 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+
+/**
+ * Guessed from the latest requests: set when one fails with `networkUnreachable`,
+ * cleared when one gets a response.
+ */
+export async function isOffline(): Promise<boolean> {
+    return await invoke("cookie-fetch", "is_offline", {}) as boolean;
+}
+
+/** Called only when the guessed status changes. */
+export async function onNetworkStatus(
+    handler: (status: { offline: boolean }) => void,
+): Promise<() => void> {
+    return await listen<{ offline: boolean }>(
+        "cookie-fetch://network-status",
+        (e) => handler(e.payload),
+    );
+}
@@ -0,0 +1,63 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type HeaderMap } from "./cookieFetch.ts";
+
+/** kept on disk, so only in-memory bodies can be queued */
+export type OutboxRequest = {
+    url: string;
+    /** `"POST"` when omitted */
+    method?: string;
+    headers?: HeaderMap;
+    body?: Uint8Array | string;
+    /** requests of one queue are sent in order; a failing one holds back those behind it but not other queues */
+    queue?: string;
+    /** `abortByTag` removes the request from the queue, aborting it when in flight */
+    tags?: string[];
+};
+
+export type OutboxItem = {
+    id: number;
+    request: OutboxRequest;
+    attempts: number;
+    lastError: string | null;
+};
+
+export type OutboxEvent =
+    | { type: "sent"; id: number; queue: string | null; status: number }
+    /** the request stays queued and is retried */
+    | { type: "failed"; id: number; queue: string | null; attempts: number; message: string }
+    /** the request can never succeed (out of scope, invalid url or method, proxy auth) and was removed */
+    | { type: "dropped"; id: number; queue: string | null; attempts: number; message: string };
+
+/**
+ * Queues `request` to be sent in order by the Rust side and returns its id.
+ * The queue is saved in the app data directory and survives restarts.
+ * Connection failures, 5xx, 408 and 429 are retried with backoff (1s doubling up to 5 minutes);
+ * any other response acknowledges the request and removes it from the queue.
+ * Errors that retrying cannot fix remove the request with a `dropped` event.
+ * Ids are never reused, even across restarts.
+ * While offline (see `isOffline`) the queue is held, probing with one request every 30s,
+ * and flushed without waiting for backoff once the network returns.
+ */
+export async function outboxEnqueue(request: OutboxRequest): Promise<number> {
+    return await invoke("cookie-fetch", "outbox_enqueue", {
+        request: compact(request),
+    }) as number;
+}
+
+/** Requests not yet acknowledged, oldest first. */
+export async function outboxItems(): Promise<OutboxItem[]> {
+    return await invoke("cookie-fetch", "outbox_items", {}) as OutboxItem[];
+}
+
+/** Drops a queued request. Returns `false` if it was already sent or removed. */
+export async function outboxRemove(id: number): Promise<boolean> {
+    return await invoke("cookie-fetch", "outbox_remove", { id }) as boolean;
+}
+
+/** Called once per send attempt of a queued request. */
+export async function onOutboxEvent(
+    handler: (event: OutboxEvent) => void,
+): Promise<() => void> {
+    return await listen<OutboxEvent>("cookie-fetch://outbox", (e) => handler(e.payload));
+}
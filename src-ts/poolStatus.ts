@@ -0,0 +1,28 @@
+import { invoke } from "./invoke.ts";
+
+/** Counted from the requests sent; connection-level counts are not exposed by the http client. */
+export type OriginStats = {
+    inFlight: number;
+    requests: number;
+    errors: number;
+    /** http version of the latest response, e.g. `"HTTP/2.0"` */
+    version: string | null;
+};
+
+export type PoolStatus = {
+    /** each client keeps its own connection pool */
+    clients: number;
+    idleClients: number;
+    /**
+     * `pool.maxSize` of the plugin config; requests wait up to `pool.waitTimeoutMs` for a free
+     * client and then reject with `poolTimeout`. Subscriptions use their own clients and are not counted.
+     */
+    maxClients: number;
+    origins: Record<string, OriginStats>;
+    /** counted per tag given in `FetchOptions` */
+    tags: Record<string, OriginStats>;
+};
+
+export async function poolStatus(): Promise<PoolStatus> {
+    return await invoke("cookie-fetch", "pool_status", {}) as PoolStatus;
+}
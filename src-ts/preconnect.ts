@@ -0,0 +1,9 @@
+import { invoke } from "./invoke.ts";
+
+/**
+ * Opens a connection to `url`'s host ahead of the first real request.
+ * A `HEAD` request is sent to `url` since the connection cannot be opened on its own.
+ */
+export async function preconnect(url: string): Promise<void> {
+    await invoke("cookie-fetch", "preconnect", { url });
+}
@@ -0,0 +1,27 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions } from "./cookieFetch.ts";
+
+/**
+ * Sends a `GET` in the background to put `url` into the plugin's `memory_cache`, so a later
+ * `cookieFetch` with the same `options` is answered without going to the network.
+ * Prefetches run one at a time. Rejects with `cacheDisabled` when `memory_cache` is not enabled.
+ */
+export async function prefetch(
+    url: string,
+    options?: Omit<FetchOptions, "method" | "body">,
+    prefetchOptions?: PrefetchOptions,
+): Promise<void> {
+    await invoke("cookie-fetch", "prefetch", {
+        url,
+        options: compact(options),
+        prefetch: compact(prefetchOptions ?? {}),
+    });
+}
+
+export interface PrefetchOptions {
+    /**
+     * How long the prefetched response stays in the cache, in milliseconds.
+     * This replaces `memory_cache.ttlMs` for the entry. Defaults to one minute.
+     */
+    ttlMs?: number;
+}
@@ -0,0 +1,23 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type Response } from "./cookieFetch.ts";
+import { withFormData } from "./formData.ts";
+
+/**
+ * Fetches `path` relative to the base url of the preset registered as `name` on the Rust side.
+ * Headers in `options` replace the preset's headers of the same name.
+ */
+export async function fetchPreset(
+    name: string,
+    path: string,
+    options?: FetchOptions,
+): Promise<Response> {
+    return await withFormData(
+        options,
+        async (options) =>
+            await invoke("cookie-fetch", "fetch_preset", {
+                preset: name,
+                path,
+                options: compact(options),
+            }) as Response,
+    );
+}
@@ -0,0 +1,32 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type HeaderMap } from "./cookieFetch.ts";
+
+export type Probe = {
+    status: number;
+    /** methods listed in `Allow` */
+    allow: string[];
+    cors: {
+        allowOrigin: string | null;
+        allowMethods: string[];
+        allowHeaders: string[];
+        exposeHeaders: string[];
+        allowCredentials: boolean;
+        maxAge: number | null;
+    };
+    server: string | null;
+    headers: HeaderMap;
+};
+
+/**
+ * Sends OPTIONS and parses the capability headers of the reply.
+ * For a CORS preflight, put `Origin` and `Access-Control-Request-Method` in `options.headers`.
+ */
+export async function probe(
+    url: string,
+    options?: Omit<FetchOptions, "method" | "body">,
+): Promise<Probe> {
+    return await invoke("cookie-fetch", "probe", {
+        url,
+        options: compact(options),
+    }) as Probe;
+}
@@ -0,0 +1,38 @@
+import { listen } from "npm:@tauri-apps/api@1/event";
+
+export type Transfer = {
+    loaded: number;
+    /** known only from `Content-Length` or the body itself */
+    total: number | null;
+    /** smoothed over recent chunks */
+    bytesPerSecond: number;
+    etaMs: number | null;
+};
+
+/**
+ * Transfer events are sent at most every 100ms and once at the end.
+ * Bodies are streamed to report upload progress, so they cannot be resent on retry.
+ * For multipart bodies, upload progress counts the bodies of all parts together, without boundaries.
+ */
+export type ProgressEvent =
+    /** the request is resent after `retryInMs` */
+    | { type: "rateLimited"; status: number; retryInMs: number }
+    | ({ type: "upload" } & Transfer)
+    | ({ type: "download" } & Transfer);
+
+export type ProgressListener = {
+    /** pass as `progress` in `FetchOptions` */
+    channel: string;
+    unlisten(): void;
+};
+
+export async function onProgress(
+    handler: (event: ProgressEvent) => void,
+): Promise<ProgressListener> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<ProgressEvent>(
+        `cookie-fetch://progress/${channel}`,
+        (e) => handler(e.payload),
+    );
+    return { channel, unlisten };
+}
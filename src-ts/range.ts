@@ -0,0 +1,39 @@
+import { invoke } from "./invoke.ts";
+import { compact, type FetchOptions, type Response } from "./cookieFetch.ts";
+import { withFormData } from "./formData.ts";
+
+/** Range returned in `Content-Range`; `end` is inclusive. */
+export type ByteRange = {
+    start: number;
+    end: number;
+    /** `null` when the server answered `*` */
+    total: number | null;
+};
+
+export type RangeResponse = {
+    response: Response;
+    range: ByteRange;
+};
+
+/**
+ * Requests bytes `start` to `end` inclusive, or to the end when `end` is omitted.
+ * Rejects with `rangeNotSatisfied` unless the server answers 206,
+ * and with `invalidContentRange` when `Content-Range` does not match the request.
+ */
+export async function fetchRange(
+    url: string,
+    start: number,
+    end?: number,
+    options?: FetchOptions,
+): Promise<RangeResponse> {
+    return await withFormData(
+        options,
+        async (options) =>
+            await invoke("cookie-fetch", "fetch_range", {
+                url,
+                start,
+                end,
+                options: compact(options),
+            }) as RangeResponse,
+    );
+}
@@ -0,0 +1,51 @@
+import { invoke } from "./invoke.ts";
+import {
+    compact,
+    type Cookies,
+    type FetchOptions,
+    type HeaderMap,
+} from "./cookieFetch.ts";
+import { withFormData } from "./formData.ts";
+
+export type ResponseHead = {
+    rid: number;
+    url: string;
+    unicodeUrl: string | null;
+    status: number;
+    headers: HeaderMap;
+    cookies: Cookies;
+    suggestedFilename: string | null;
+    remoteAddress: string | null;
+    localAddress: string | null;
+    proxy: string | null;
+};
+
+export async function fetchBegin(
+    url: string,
+    options?: FetchOptions,
+): Promise<ResponseHead> {
+    return await withFormData(
+        options,
+        async (options) =>
+            await invoke("cookie-fetch", "fetch_begin", {
+                url,
+                options: compact(options),
+            }) as ResponseHead,
+    );
+}
+
+/** Resolves to `null` once the whole body has been read; `rid` is released at that point. */
+export async function readChunk(
+    rid: number,
+    maxBytes: number,
+): Promise<Uint8Array | null> {
+    return await invoke("cookie-fetch", "read_chunk", {
+        rid,
+        max: maxBytes,
+    }) as Uint8Array | null;
+}
+
+/** Readers left unread for 5 minutes are released as well, after which `readChunk` fails. */
+export async function closeBody(rid: number): Promise<void> {
+    await invoke("cookie-fetch", "close_body", { rid });
+}
@@ -0,0 +1,41 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type HeaderMap } from "./cookieFetch.ts";
+
+export type RedirectHop = {
+    status: number;
+    url: string;
+    /** resolved against `url` */
+    location: string;
+    /** cookie values are redacted unless `redaction.revealCookies` is set */
+    headers: HeaderMap;
+};
+
+export type RedirectDecision = {
+    follow: boolean;
+    /** follow to this url instead of `location` */
+    location?: string;
+    /** replaces headers of the same name on the next request */
+    headers?: HeaderMap;
+};
+
+/**
+ * Handles hops of requests sent with `redirect: "interactive"`.
+ * Not following (or not replying within `interactive_redirect.timeoutMs` of the plugin config, 60s by default) resolves the request with the 3xx response,
+ * which is how an intermediate redirect to a custom scheme can be captured.
+ */
+export async function onRedirect(
+    handler: (hop: RedirectHop) => RedirectDecision | Promise<RedirectDecision>,
+): Promise<() => void> {
+    return await listen<RedirectHop & { id: number }>(
+        "cookie-fetch://redirect",
+        async (e) => {
+            const { id, ...hop } = e.payload;
+            const reply = await handler(hop);
+            await invoke("cookie-fetch", "redirect_reply", {
+                id,
+                reply: compact(reply),
+            });
+        },
+    );
+}
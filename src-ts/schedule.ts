@@ -0,0 +1,61 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { type BatchResult } from "./batch.ts";
+import { compact, type FetchOptions, type Response } from "./cookieFetch.ts";
+import type { Subscription } from "./sse.ts";
+
+export type ScheduleOptions = {
+    /** at least 1000 */
+    intervalMs: number;
+    /** send the first request right away instead of after `intervalMs` */
+    immediate?: boolean;
+};
+
+/**
+ * Fetches `url` every `intervalMs` with the same cookie jar and passes each result to `onResult`.
+ * The job runs on the Rust side, so it keeps going while the webview is busy or its windows are closed,
+ * until `close()` is called. Results delivered while no window is open are dropped.
+ */
+export async function schedule(
+    url: string,
+    scheduleOptions: ScheduleOptions,
+    options: FetchOptions | undefined,
+    onResult: (result: BatchResult) => void,
+): Promise<Subscription> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<BatchResult>(
+        `cookie-fetch://schedule/${channel}`,
+        (e) => onResult(decode(e.payload)),
+    );
+
+    try {
+        await invoke("cookie-fetch", "schedule", {
+            url,
+            options: compact(options),
+            schedule: compact(scheduleOptions),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async close() {
+            unlisten();
+            await invoke("cookie-fetch", "unsubscribe", { channel });
+        },
+    };
+}
+
+// events are JSON, so binary bodies arrive as arrays of numbers
+function decode(result: BatchResult): BatchResult {
+    if (!("ok" in result)) {
+        return result;
+    }
+    const body = result.ok.body as Response["body"] | number[];
+    if (Array.isArray(body)) {
+        return { ok: { ...result.ok, body: Uint8Array.from(body) } };
+    }
+    return result;
+}
@@ -0,0 +1,180 @@
+import { type FetchOptions } from "./cookieFetch.ts";
+import { fetch as cookieFetch } from "./fetch.ts";
+
+export type ShimOptions = {
+    /** origins routed through the plugin, e.g. `https://api.example.com` */
+    origins: string[];
+    /** plugin specific options applied to every routed request */
+    options?: FetchOptions;
+    /** also patch `XMLHttpRequest`. defaults to `true` */
+    xhr?: boolean;
+};
+
+/**
+ * Patches `window.fetch` (and `XMLHttpRequest`) so that requests to `origins` go through the plugin.
+ * Other requests pass through to the original implementation. Returns a function restoring the originals.
+ */
+export function installFetchShim(shim: ShimOptions): () => void {
+    const origins = new Set(shim.origins.map((o) => new URL(o).origin));
+    const routed = (url: string | URL) =>
+        origins.has(new URL(url, location.href).origin);
+
+    const originalFetch = globalThis.fetch;
+    globalThis.fetch = (input: RequestInfo | URL, init?: RequestInit) => {
+        const url = input instanceof Request ? input.url : input;
+        if (!routed(url)) {
+            return originalFetch(input, init);
+        }
+        return cookieFetch(input, init, shim.options);
+    };
+
+    const OriginalXHR = globalThis.XMLHttpRequest;
+    if (shim.xhr ?? true) {
+        globalThis.XMLHttpRequest = createXHR(OriginalXHR, routed, shim.options);
+    }
+
+    return () => {
+        globalThis.fetch = originalFetch;
+        globalThis.XMLHttpRequest = OriginalXHR;
+    };
+}
+
+function createXHR(
+    OriginalXHR: typeof XMLHttpRequest,
+    routed: (url: string | URL) => boolean,
+    options: FetchOptions | undefined,
+): typeof XMLHttpRequest {
+    // requests to other origins are delegated to the original implementation
+    return class extends OriginalXHR {
+        #routed = false;
+        #method = "GET";
+        #url = "";
+        #headers = new Headers();
+        #abort = new AbortController();
+        #readyState: number = OriginalXHR.UNSENT;
+        #status = 0;
+        #statusText = "";
+        #responseURL = "";
+        #responseHeaders = new Headers();
+        #response: unknown = null;
+        #responseText = "";
+
+        open(method: string, url: string | URL, ...rest: unknown[]) {
+            this.#routed = routed(url);
+            if (!this.#routed) {
+                // @ts-ignore: forwards the optional async/user/password arguments
+                return super.open(method, url, ...rest);
+            }
+            this.#method = method;
+            this.#url = new URL(url, location.href).href;
+            this.#setReadyState(OriginalXHR.OPENED);
+        }
+
+        setRequestHeader(name: string, value: string) {
+            if (!this.#routed) {
+                return super.setRequestHeader(name, value);
+            }
+            this.#headers.append(name, value);
+        }
+
+        send(body?: Document | XMLHttpRequestBodyInit | null) {
+            if (!this.#routed) {
+                return super.send(body);
+            }
+            const init: RequestInit = {
+                method: this.#method,
+                headers: this.#headers,
+                body: body instanceof Document ? new XMLSerializer().serializeToString(body) : body,
+                signal: this.#abort.signal,
+            };
+            this.dispatchEvent(new ProgressEvent("loadstart"));
+            cookieFetch(this.#url, init, options).then(async (res) => {
+                this.#status = res.status;
+                this.#statusText = res.statusText;
+                this.#responseURL = res.url;
+                this.#responseHeaders = res.headers;
+                this.#setReadyState(OriginalXHR.HEADERS_RECEIVED);
+                this.#setReadyState(OriginalXHR.LOADING);
+                const buf = await res.arrayBuffer();
+                this.#responseText = new TextDecoder().decode(buf);
+                this.#response = this.#decode(buf);
+                this.#setReadyState(OriginalXHR.DONE);
+                this.dispatchEvent(new ProgressEvent("load"));
+                this.dispatchEvent(new ProgressEvent("loadend"));
+            }).catch((e) => {
+                this.#setReadyState(OriginalXHR.DONE);
+                const type = e instanceof DOMException && e.name === "AbortError"
+                    ? "abort"
+                    : "error";
+                this.dispatchEvent(new ProgressEvent(type));
+                this.dispatchEvent(new ProgressEvent("loadend"));
+            });
+        }
+
+        abort() {
+            if (!this.#routed) {
+                return super.abort();
+            }
+            this.#abort.abort();
+        }
+
+        getResponseHeader(name: string) {
+            if (!this.#routed) {
+                return super.getResponseHeader(name);
+            }
+            return this.#responseHeaders.get(name);
+        }
+
+        getAllResponseHeaders() {
+            if (!this.#routed) {
+                return super.getAllResponseHeaders();
+            }
+            return [...this.#responseHeaders]
+                .map(([name, value]) => `${name}: ${value}\r\n`)
+                .join("");
+        }
+
+        get readyState() {
+            return this.#routed ? this.#readyState : super.readyState;
+        }
+        get status() {
+            return this.#routed ? this.#status : super.status;
+        }
+        get statusText() {
+            return this.#routed ? this.#statusText : super.statusText;
+        }
+        get responseURL() {
+            return this.#routed ? this.#responseURL : super.responseURL;
+        }
+        get response() {
+            return this.#routed ? this.#response : super.response;
+        }
+        get responseText() {
+            return this.#routed ? this.#responseText : super.responseText;
+        }
+
+        #decode(buf: ArrayBuffer): unknown {
+            switch (this.responseType) {
+                case "arraybuffer":
+                    return buf;
+                case "blob":
+                    return new Blob([buf], {
+                        type: this.#responseHeaders.get("content-type") ?? "",
+                    });
+                case "json":
+                    try {
+                        return JSON.parse(this.#responseText);
+                    } catch {
+                        return null;
+                    }
+                default:
+                    return this.#responseText;
+            }
+        }
+
+        #setReadyState(state: number) {
+            this.#readyState = state;
+            this.dispatchEvent(new Event("readystatechange"));
+        }
+    };
+}
@@ -0,0 +1,60 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type FetchOptions } from "./cookieFetch.ts";
+
+export type SseEvent = {
+    id?: string;
+    event: string;
+    data: string;
+    retry?: number;
+};
+
+export type SseMessage =
+    | { type: "open" }
+    | ({ type: "event" } & SseEvent)
+    | { type: "error"; message: string }
+    | { type: "close" };
+
+export type Subscription = {
+    close(): Promise<void>;
+};
+
+/**
+ * Reconnects after the server's `retry` when the connection fails or ends.
+ * A non-200 response, or a request rejected by the scope, a redirect guard or the proxy,
+ * sends `error` and then `close` instead.
+ */
+export async function sse(
+    url: string,
+    options: FetchOptions | undefined,
+    onMessage: (message: SseMessage) => void,
+): Promise<Subscription> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<SseMessage>(
+        `cookie-fetch://sse/${channel}`,
+        (e) => {
+            onMessage(e.payload);
+            if (e.payload.type === "close") {
+                unlisten();
+            }
+        },
+    );
+
+    try {
+        await invoke("cookie-fetch", "sse", {
+            url,
+            options: compact(options),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async close() {
+            unlisten();
+            await invoke("cookie-fetch", "unsubscribe", { channel });
+        },
+    };
+}
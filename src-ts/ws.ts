@@ -0,0 +1,52 @@
+import { invoke } from "./invoke.ts";
+import { listen } from "npm:@tauri-apps/api@1/event";
+import { compact, type FetchOptions } from "./cookieFetch.ts";
+
+export type WsMessage =
+    | { type: "open" }
+    | { type: "text"; data: string }
+    | { type: "binary"; data: number[] }
+    | { type: "error"; message: string }
+    | { type: "close"; code?: number; reason?: string };
+
+export type WebSocketConnection = {
+    send(data: string | Uint8Array): Promise<void>;
+    close(code?: number, reason?: string): Promise<void>;
+};
+
+export async function wsConnect(
+    url: string,
+    options: FetchOptions | undefined,
+    onMessage: (message: WsMessage) => void,
+): Promise<WebSocketConnection> {
+    const channel = crypto.randomUUID();
+    const unlisten = await listen<WsMessage>(
+        `cookie-fetch://ws/${channel}`,
+        (e) => {
+            onMessage(e.payload);
+            if (e.payload.type === "close") {
+                unlisten();
+            }
+        },
+    );
+
+    try {
+        await invoke("cookie-fetch", "ws_connect", {
+            url,
+            options: compact(options),
+            channel,
+        });
+    } catch (e) {
+        unlisten();
+        throw e;
+    }
+
+    return {
+        async send(data) {
+            await invoke("cookie-fetch", "ws_send", { channel, data });
+        },
+        async close(code, reason) {
+            await invoke("cookie-fetch", "ws_close", { channel, code, reason });
+        },
+    };
+}
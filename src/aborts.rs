@@ -0,0 +1,73 @@
+use crate::cookie_fetch::FetchError;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::oneshot;
+
+/// タグの付いた実行中のリクエスト。`abort`でタグごとに中断する。
+pub struct Aborts {
+    next_id: AtomicU64,
+    tagged: Mutex<HashMap<u64, (Vec<String>, oneshot::Sender<()>)>>,
+}
+
+impl Aborts {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            tagged: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 中断された場合は`FetchError::Aborted`になる。`tags`が空の場合は中断できない。
+    pub async fn run<T>(
+        &self,
+        tags: &[String],
+        fut: impl Future<Output = Result<T, FetchError>>,
+    ) -> Result<T, FetchError> {
+        if tags.is_empty() {
+            return fut.await;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.tagged.lock().unwrap().insert(id, (tags.to_vec(), tx));
+        let _guard = Registered { aborts: self, id };
+
+        tokio::select! {
+            result = fut => result,
+            _ = rx => Err(FetchError::Aborted),
+        }
+    }
+
+    /// 中断したリクエストの数を返す。
+    pub fn abort(&self, tag: &str) -> usize {
+        let mut tagged = self.tagged.lock().unwrap();
+        let ids: Vec<u64> = tagged
+            .iter()
+            .filter(|(_, (tags, _))| tags.iter().any(|t| t == tag))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids.iter() {
+            if let Some((_, tx)) = tagged.remove(id) {
+                let _ = tx.send(());
+            }
+        }
+        ids.len()
+    }
+}
+
+struct Registered<'a> {
+    aborts: &'a Aborts,
+    id: u64,
+}
+
+impl Drop for Registered<'_> {
+    fn drop(&mut self) {
+        self.aborts.tagged.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,26 @@
+/// 401/407のレスポンスの内容
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthChallenge {
+    pub status: u16,
+    pub url: String,
+    /// `WWW-Authenticate`または`Proxy-Authenticate`の値
+    pub challenges: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    Basic {
+        username: String,
+        password: String,
+    },
+    /// `Authorization`ヘッダーの値をそのまま使う
+    Raw(String),
+}
+
+/// `FetchOptions`の`authChallenge`が有効なリクエストで、認証を求められたときに呼ばれる。
+/// `None`を返した場合はフロントエンドに問い合わせる。`Builder::auth_provider`で登録する。
+#[async_trait::async_trait]
+pub trait AuthProvider: Send + Sync {
+    async fn credentials(&self, challenge: &AuthChallenge) -> Option<Credentials>;
+}
@@ -0,0 +1,14 @@
+use bytes::Bytes;
+use std::{error::Error, pin::Pin};
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+pub type BodyStream =
+    Pin<Box<dyn futures_util::Stream<Item = Result<Bytes, BoxError>> + Send + Sync>>;
+
+/// `FetchOptions`の`body: { provider: name }`から参照されるリクエストボディの供給元。
+/// `Builder::body_provider`で登録する。
+#[async_trait::async_trait]
+pub trait BodyProvider: Send + Sync {
+    async fn provide(&self, url: &reqwest::Url) -> Result<BodyStream, BoxError>;
+}
@@ -0,0 +1,28 @@
+use crate::body_provider::{BodyStream, BoxError};
+use bytes::Bytes;
+use std::pin::Pin;
+
+pub type ResponseStream = Pin<Box<dyn futures_util::Stream<Item = Result<Bytes, BoxError>> + Send>>;
+
+/// レスポンスのボディをフロントエンドに渡す前に変換する。`Builder::response_transform`で登録する。
+/// 同じURLに複数マッチした場合は登録した順に適用される。
+pub trait ResponseTransform: Send + Sync {
+    fn transform(
+        &self,
+        url: &reqwest::Url,
+        headers: &reqwest::header::HeaderMap,
+        body: ResponseStream,
+    ) -> ResponseStream;
+}
+
+/// リクエストのボディを送る前に変換する。`Builder::request_transform`で登録する。
+/// 変換したボディは長さがわからないためchunkedで送られる。multipartのボディにはpartごとに適用される。
+pub trait RequestTransform: Send + Sync {
+    /// `headers`に入れたヘッダーがリクエストに追加される。multipartの場合はpartのヘッダーになる。
+    fn transform(
+        &self,
+        url: &reqwest::Url,
+        headers: &mut reqwest::header::HeaderMap,
+        body: BodyStream,
+    ) -> BodyStream;
+}
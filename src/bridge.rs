@@ -0,0 +1,100 @@
+use crate::CookieClient;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::HashMap;
+
+/// cookieとヘッダーの間で値を受け渡す規則。
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeConfig {
+    #[serde(default)]
+    pub cookie_to_header: Vec<CookieToHeader>,
+    #[serde(default)]
+    pub header_to_cookie: Vec<HeaderToCookie>,
+}
+
+/// `url`にマッチするリクエストで、cookie `cookie`の値をヘッダー`header`として送る。
+#[serde_with::serde_as]
+#[derive(Debug, serde::Deserialize)]
+pub struct CookieToHeader {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub url: glob::Pattern,
+    pub cookie: String,
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub header: HeaderName,
+    /// `{value}`をcookieの値に置き換えたものをヘッダーの値にする。省略した場合は値をそのまま使う。
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// `url`にマッチするレスポンスのヘッダー`header`の値を、cookie `cookie`としてjarに保存する。
+#[serde_with::serde_as]
+#[derive(Debug, serde::Deserialize)]
+pub struct HeaderToCookie {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub url: glob::Pattern,
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub header: HeaderName,
+    pub cookie: String,
+}
+
+/// `ephemeral_cookies`はjarのcookieより優先する。
+pub fn cookie_value(
+    client: &CookieClient,
+    ephemeral_cookies: &HashMap<String, String>,
+    url: &reqwest::Url,
+    name: &str,
+) -> Option<String> {
+    match ephemeral_cookies.get(name) {
+        Some(v) => Some(v.clone()),
+        None => client
+            .cookie_store()
+            .get_request_values(url)
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.to_string()),
+    }
+}
+
+/// ヘッダーが明示されている場合は上書きしない。
+pub fn apply_cookie_to_header(
+    rules: &[CookieToHeader],
+    client: &CookieClient,
+    ephemeral_cookies: &HashMap<String, String>,
+    url: &reqwest::Url,
+    headers: &mut HeaderMap,
+) {
+    for rule in rules {
+        if !rule.url.matches(url.as_str()) || headers.contains_key(&rule.header) {
+            continue;
+        }
+        let Some(value) = cookie_value(client, ephemeral_cookies, url, &rule.cookie) else {
+            continue;
+        };
+        let value = match &rule.template {
+            Some(template) => template.replace("{value}", &value),
+            None => value,
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(rule.header.clone(), value);
+        }
+    }
+}
+
+/// Set-Cookieと同様に扱うため、`storeResponseCookies`が無効な場合は保存しない。
+pub fn apply_header_to_cookie(
+    rules: &[HeaderToCookie],
+    client: &CookieClient,
+    res: &reqwest::Response,
+) {
+    let url = res.url();
+    let set_cookies: Vec<HeaderValue> = rules
+        .iter()
+        .filter(|rule| rule.url.matches(url.as_str()))
+        .filter_map(|rule| {
+            let value = res.headers().get(&rule.header)?.to_str().ok()?;
+            HeaderValue::from_str(&format!("{}={}; Path=/", rule.cookie, value)).ok()
+        })
+        .collect();
+    if !set_cookies.is_empty() {
+        client.store_cookies(&set_cookies, url);
+    }
+}
@@ -1,7 +1,55 @@
-use crate::scope::Scope;
+use crate::{
+    bridge::BridgeConfig,
+    cookie_client::{Http2Config, PoolConfig},
+    cookie_fetch::{
+        AuthChallengeConfig, ConsentConfig, InteractiveRedirectConfig, InterceptConfig,
+        MemoryCacheConfig, RetryConfig,
+    },
+    cookie_journal::CookieJournalConfig,
+    csrf::CsrfRule,
+    logger::LogConfig,
+    proxy::ProxyConfig,
+    redaction::Redaction,
+    scope::Scope,
+    throttle::BandwidthConfig,
+};
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub scope: Scope,
+    #[serde(default)]
+    pub consent: ConsentConfig,
+    #[serde(default)]
+    pub redaction: Redaction,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub intercept: InterceptConfig,
+    #[serde(default)]
+    pub auth_challenge: AuthChallengeConfig,
+    #[serde(default)]
+    pub interactive_redirect: InteractiveRedirectConfig,
+    /// 実行中は`set_config`で変えられる。
+    #[serde(default)]
+    pub proxies: Vec<ProxyConfig>,
+    /// `set_config`を許可する。
+    #[serde(default)]
+    pub allow_set_config: bool,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub http2: Http2Config,
+    #[serde(default)]
+    pub pool: PoolConfig,
+    #[serde(default)]
+    pub csrf: Vec<CsrfRule>,
+    #[serde(default)]
+    pub bridge: BridgeConfig,
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    #[serde(default)]
+    pub memory_cache: MemoryCacheConfig,
+    #[serde(default)]
+    pub cookie_journal: CookieJournalConfig,
 }
@@ -1,11 +1,216 @@
-use reqwest::redirect::{self, Attempt};
-use std::sync::{Arc, Mutex, MutexGuard};
+use crate::{cookie_fetch::FetchError, cookie_journal::CookieJournal, scope::ScopeCheck};
+use deadpool::managed::Manager;
+use reqwest::{
+    cookie::CookieStore,
+    header::HeaderValue,
+    redirect::{self, Attempt},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+    time::Duration,
+};
 
-struct ClientPoolManager;
+const MAX_CONNECT_TIMEOUT_CLIENTS: usize = 8;
+
+struct ClientPoolManager {
+    settings: RwLock<Arc<ClientSettings>>,
+}
+
+/// プールの全てのclientに共通する設定
+#[derive(Debug, Clone, Default)]
+pub struct ClientSettings {
+    /// 環境変数のプロキシより先に順に試される。
+    pub proxies: Vec<reqwest::Proxy>,
+    /// リクエストで指定されなかった場合の接続のタイムアウト
+    pub connect_timeout: Option<Duration>,
+    pub http2: Http2Config,
+    pub tls_backend: Option<TlsBackend>,
+    /// 全てのclientのjarの変更を記録する。
+    pub(crate) journal: Option<Arc<CookieJournal>>,
+}
+
+/// プールの大きさ。購読のように長く使われるclientはプールの外で作るため、ここには数えない。
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolConfig {
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
+    /// 全てのclientが使用中の場合に待つ時間。過ぎると`FetchError::PoolTimeout`になる。
+    #[serde(default = "default_wait_timeout_ms")]
+    pub wait_timeout_ms: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: default_max_size(),
+            wait_timeout_ms: default_wait_timeout_ms(),
+        }
+    }
+}
+
+fn default_max_size() -> usize {
+    32
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    30 * 1000
+}
+
+/// 複数のTLSのfeatureを有効にした場合に使う実装。省略した場合はnative-tlsが優先される。
+#[derive(Debug, Clone, Copy)]
+pub enum TlsBackend {
+    #[cfg(feature = "native-tls")]
+    Native,
+    #[cfg(any(feature = "rustls-webpki-roots", feature = "rustls-native-roots"))]
+    Rustls,
+}
+
+/// 省略した値はhyperの既定値になる。
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Http2Config {
+    #[serde(default)]
+    pub initial_stream_window_size: Option<u32>,
+    #[serde(default)]
+    pub initial_connection_window_size: Option<u32>,
+    /// 有効な場合、window sizeの指定は無視される。
+    #[serde(default)]
+    pub adaptive_window: bool,
+    #[serde(default)]
+    pub max_frame_size: Option<u32>,
+    #[serde(default)]
+    pub keep_alive_interval_ms: Option<u64>,
+    #[serde(default)]
+    pub keep_alive_timeout_ms: Option<u64>,
+    /// `false`の場合、開いているstreamがない間はpingを送らない。
+    #[serde(default)]
+    pub keep_alive_while_idle: bool,
+}
+
+impl Http2Config {
+    fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let mut builder = builder
+            .http2_initial_stream_window_size(self.initial_stream_window_size)
+            .http2_initial_connection_window_size(self.initial_connection_window_size)
+            .http2_adaptive_window(self.adaptive_window)
+            .http2_max_frame_size(self.max_frame_size)
+            .http2_keep_alive_interval(self.keep_alive_interval_ms.map(Duration::from_millis))
+            .http2_keep_alive_while_idle(self.keep_alive_while_idle);
+        if let Some(timeout) = self.keep_alive_timeout_ms {
+            builder = builder.http2_keep_alive_timeout(Duration::from_millis(timeout));
+        }
+        builder
+    }
+}
 pub struct CookieClient {
     client: reqwest::Client,
-    cookie_store: Arc<reqwest_cookie_store::CookieStoreMutex>,
+    /// `set_override`で作った、このリクエストだけで使うclient
+    override_client: Option<reqwest::Client>,
+    /// 接続のタイムアウトだけを変えるclient。値の種類は少ないため、作ったものを使い回す。
+    connect_timeout_clients: HashMap<Duration, reqwest::Client>,
+    settings: Arc<ClientSettings>,
+    cookie_jar: Arc<CookieJar>,
     redirect_policy: Arc<Mutex<RedirectPolicy>>,
+    redirect_guard: Arc<Mutex<RedirectGuard>>,
+}
+
+/// 接続の仕方を変えるため、プールのclientの代わりに使うclientの設定
+#[derive(Debug, Default)]
+pub struct ClientOverride {
+    /// このドメインへの接続をDNSを引かずにこのアドレスへ向ける。
+    pub resolve: Option<(String, Vec<SocketAddr>)>,
+    pub proxy: Option<ProxyOverride>,
+    pub connect_timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum ProxyOverride {
+    Direct,
+    Proxy(reqwest::Proxy),
+}
+
+/// cookie 0.17は`Partitioned`属性を解釈しないため、Set-Cookieから読み取って別に記録する。
+struct CookieJar {
+    store: reqwest_cookie_store::CookieStoreMutex,
+    /// partitioned cookieの(domain, name)
+    partitioned: Mutex<HashSet<(String, String)>>,
+    partition_key: Mutex<Option<String>>,
+    store_response_cookies: AtomicBool,
+    journal: Option<Arc<CookieJournal>>,
+    /// journalに記録する、送信中のリクエストのログのid
+    request_id: Mutex<Option<u64>>,
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(
+        &self,
+        cookie_headers: &mut dyn Iterator<Item = &HeaderValue>,
+        url: &reqwest::Url,
+    ) {
+        if !self.store_response_cookies.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let cookie_headers: Vec<&HeaderValue> = cookie_headers.collect();
+
+        {
+            let mut partitioned = self.partitioned.lock().unwrap();
+            for header in cookie_headers.iter() {
+                if let Some(key) = header.to_str().ok().and_then(|h| partitioned_key(h, url)) {
+                    partitioned.insert(key);
+                }
+            }
+        }
+
+        let Some(journal) = &self.journal else {
+            return self.store.set_cookies(&mut cookie_headers.into_iter(), url);
+        };
+        let pending: Vec<_> = {
+            let store = self.store.lock().unwrap();
+            cookie_headers
+                .iter()
+                .filter_map(|header| header.to_str().ok())
+                .filter_map(|header| journal.before(&store, header, url))
+                .collect()
+        };
+
+        self.store.set_cookies(&mut cookie_headers.into_iter(), url);
+
+        let store = self.store.lock().unwrap();
+        let request_id = *self.request_id.lock().unwrap();
+        for pending in pending {
+            journal.record(&store, pending, request_id);
+        }
+    }
+
+    fn cookies(&self, url: &reqwest::Url) -> Option<HeaderValue> {
+        self.store.cookies(url)
+    }
+}
+
+fn partitioned_key(set_cookie: &str, url: &reqwest::Url) -> Option<(String, String)> {
+    let partitioned = set_cookie
+        .split(';')
+        .skip(1)
+        .any(|attr| attr.trim().eq_ignore_ascii_case("partitioned"));
+    if !partitioned {
+        return None;
+    }
+
+    let cookie = cookie::Cookie::parse(set_cookie).ok()?;
+    let domain = match cookie.domain() {
+        Some(v) => v.trim_start_matches('.').to_ascii_lowercase(),
+        None => url.host_str()?.to_string(),
+    };
+
+    Some((domain, cookie.name().to_string()))
 }
 
 pub enum RedirectPolicy {
@@ -37,26 +242,186 @@ impl RedirectPolicy {
     }
 }
 
+/// リダイレクトで許可しない遷移。`RedirectPolicy`より先に確認する。
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedirectGuard {
+    /// httpsからhttpへのリダイレクトを拒否する。
+    #[serde(default)]
+    pub deny_downgrade: bool,
+    /// 空でない場合、これ以外のホストへのリダイレクトを拒否する。
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// リクエストを送ったコマンドのscope
+    #[serde(skip)]
+    pub(crate) scope: Option<ScopeCheck>,
+}
+
+impl RedirectGuard {
+    pub fn check(&self, from: &reqwest::Url, to: &reqwest::Url) -> Result<(), RedirectBlocked> {
+        let downgrade = from.scheme() == "https" && to.scheme() == "http";
+        if self.deny_downgrade && downgrade {
+            return Err(RedirectBlocked(to.clone()));
+        }
+
+        let allowed = self.allowed_hosts.is_empty()
+            || to.host_str().is_some_and(|host| {
+                self.allowed_hosts
+                    .iter()
+                    .any(|h| h.eq_ignore_ascii_case(host))
+            });
+        if !allowed {
+            return Err(RedirectBlocked(to.clone()));
+        }
+
+        if let Some(scope) = &self.scope {
+            if !scope.is_allowed(to) {
+                return Err(RedirectBlocked(to.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `RedirectGuard`に拒否されたリダイレクト先
+#[derive(Debug)]
+pub struct RedirectBlocked(pub reqwest::Url);
+
+impl std::fmt::Display for RedirectBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "redirect to `{}` blocked", self.0)
+    }
+}
+impl std::error::Error for RedirectBlocked {}
+
 fn default_redirect_policy() -> RedirectPolicy {
     RedirectPolicy::limited(10)
 }
 
 impl CookieClient {
+    fn client(&self) -> &reqwest::Client {
+        self.override_client.as_ref().unwrap_or(&self.client)
+    }
+
     pub fn request<U: reqwest::IntoUrl>(
         &self,
         method: reqwest::Method,
         url: U,
     ) -> reqwest::RequestBuilder {
-        self.client.request(method, url)
+        self.client().request(method, url)
+    }
+
+    pub async fn execute(&self, request: reqwest::Request) -> reqwest::Result<reqwest::Response> {
+        self.client().execute(request).await
+    }
+
+    /// cookie jarとリダイレクトの設定を共有したまま、接続の仕方だけを変える。
+    pub fn set_override(&mut self, client_override: &ClientOverride) -> reqwest::Result<()> {
+        if let ClientOverride {
+            resolve: None,
+            proxy: None,
+            connect_timeout: Some(timeout),
+        } = client_override
+        {
+            return self.set_connect_timeout(*timeout);
+        }
+        let client = build_client(
+            &self.settings,
+            &self.cookie_jar,
+            &self.redirect_policy,
+            &self.redirect_guard,
+            client_override,
+        )?;
+        self.override_client = Some(client);
+        Ok(())
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Duration) -> reqwest::Result<()> {
+        if let Some(client) = self.connect_timeout_clients.get(&timeout) {
+            self.override_client = Some(client.clone());
+            return Ok(());
+        }
+        if self.connect_timeout_clients.len() >= MAX_CONNECT_TIMEOUT_CLIENTS {
+            self.connect_timeout_clients.clear();
+        }
+        let client = build_client(
+            &self.settings,
+            &self.cookie_jar,
+            &self.redirect_policy,
+            &self.redirect_guard,
+            &ClientOverride {
+                connect_timeout: Some(timeout),
+                ..ClientOverride::default()
+            },
+        )?;
+        self.connect_timeout_clients.insert(timeout, client.clone());
+        self.override_client = Some(client);
+        Ok(())
+    }
+
+    pub fn clear_override(&mut self) {
+        self.override_client = None;
     }
 
     pub fn cookie_store<'a>(&'a self) -> MutexGuard<'a, reqwest_cookie_store::CookieStore> {
-        self.cookie_store.lock().unwrap()
+        self.cookie_jar.store.lock().unwrap()
+    }
+
+    /// このリクエストのpartition key。CHIPSでは埋め込み元のトップレベルサイトであり、
+    /// リクエストURLからは決まらないため、呼び出し側が渡す。`None`の場合partitioned cookieは送れない。
+    pub fn set_partition_key(&self, partition_key: Option<String>) {
+        *self.cookie_jar.partition_key.lock().unwrap() = partition_key;
+    }
+
+    /// `false`の場合、レスポンスのSet-Cookieを無視する。
+    pub fn set_store_response_cookies(&self, store: bool) {
+        self.cookie_jar
+            .store_response_cookies
+            .store(store, Ordering::Relaxed);
+    }
+
+    /// 続くレスポンスのcookieの変更を、このidのリクエストのものとしてjournalに記録する。
+    pub fn set_request_id(&self, id: Option<u64>) {
+        *self.cookie_jar.request_id.lock().unwrap() = id;
+    }
+
+    pub fn partition_key(&self) -> Option<String> {
+        self.cookie_jar.partition_key.lock().unwrap().clone()
+    }
+
+    pub fn mark_partitioned(&self, domain: String, name: String) {
+        self.cookie_jar
+            .partitioned
+            .lock()
+            .unwrap()
+            .insert((domain, name));
+    }
+
+    pub fn is_partitioned(&self, domain: &str, name: &str) -> bool {
+        self.cookie_jar
+            .partitioned
+            .lock()
+            .unwrap()
+            .contains(&(domain.to_string(), name.to_string()))
     }
 
     pub fn redirect_policy<'a>(&'a self) -> MutexGuard<'a, RedirectPolicy> {
         self.redirect_policy.lock().unwrap()
     }
+
+    pub fn redirect_guard<'a>(&'a self) -> MutexGuard<'a, RedirectGuard> {
+        self.redirect_guard.lock().unwrap()
+    }
+
+    /// レスポンスのSet-Cookieと同様にjarに保存する。
+    pub fn store_cookies(&self, set_cookies: &[HeaderValue], url: &reqwest::Url) {
+        self.cookie_jar.set_cookies(&mut set_cookies.iter(), url)
+    }
+
+    pub fn cookie_header(&self, url: &reqwest::Url) -> Option<reqwest::header::HeaderValue> {
+        self.cookie_jar.cookies(url)
+    }
 }
 
 #[async_trait::async_trait]
@@ -65,31 +430,37 @@ impl deadpool::managed::Manager for ClientPoolManager {
     type Error = reqwest::Error;
 
     async fn create(&self) -> Result<Self::Type, Self::Error> {
+        let settings = Arc::clone(&self.settings.read().unwrap());
         let redirect_policy = default_redirect_policy();
         let redirect_policy = Mutex::new(redirect_policy);
         let redirect_policy = Arc::new(redirect_policy);
+        let redirect_guard = Arc::new(Mutex::new(RedirectGuard::default()));
 
         let cookie_store = reqwest_cookie_store::CookieStore::new(None);
-        let cookie_store = reqwest_cookie_store::CookieStoreMutex::new(cookie_store);
-        let cookie_store = Arc::new(cookie_store);
-        let client = reqwest::Client::builder()
-            .cookie_provider(Arc::clone(&cookie_store))
-            .redirect(redirect::Policy::custom({
-                let policy = redirect_policy.clone();
-                move |a| {
-                    if let Ok(mut c) = policy.lock() {
-                        c.check(a)
-                    } else {
-                        a.stop()
-                    }
-                }
-            }))
-            .build()?;
+        let cookie_jar = Arc::new(CookieJar {
+            store: reqwest_cookie_store::CookieStoreMutex::new(cookie_store),
+            partitioned: Mutex::new(HashSet::new()),
+            partition_key: Mutex::new(None),
+            store_response_cookies: AtomicBool::new(true),
+            journal: settings.journal.clone(),
+            request_id: Mutex::new(None),
+        });
+        let client = build_client(
+            &settings,
+            &cookie_jar,
+            &redirect_policy,
+            &redirect_guard,
+            &ClientOverride::default(),
+        )?;
 
         Ok(CookieClient {
             client,
-            cookie_store,
+            override_client: None,
+            connect_timeout_clients: HashMap::new(),
+            settings,
+            cookie_jar,
             redirect_policy,
+            redirect_guard,
         })
     }
 
@@ -98,30 +469,173 @@ impl deadpool::managed::Manager for ClientPoolManager {
         value: &mut Self::Type,
         _: &deadpool::managed::Metrics,
     ) -> deadpool::managed::RecycleResult<Self::Error> {
+        if !Arc::ptr_eq(&value.settings, &self.settings.read().unwrap()) {
+            return Err(deadpool::managed::RecycleError::StaticMessage(
+                "client settings changed",
+            ));
+        }
+        value.clear_override();
         let mut cookie_store = value.cookie_store();
         cookie_store.clear();
+        value.cookie_jar.partitioned.lock().unwrap().clear();
+        *value.cookie_jar.partition_key.lock().unwrap() = None;
+        value.set_request_id(None);
+        value.set_store_response_cookies(true);
         let mut redirect_policy = value.redirect_policy();
         *redirect_policy = default_redirect_policy();
+        *value.redirect_guard() = RedirectGuard::default();
         Ok(())
     }
 }
 
+fn build_client(
+    settings: &ClientSettings,
+    cookie_jar: &Arc<CookieJar>,
+    redirect_policy: &Arc<Mutex<RedirectPolicy>>,
+    redirect_guard: &Arc<Mutex<RedirectGuard>>,
+    client_override: &ClientOverride,
+) -> reqwest::Result<reqwest::Client> {
+    let mut builder = settings.http2.apply(reqwest::Client::builder());
+    match settings.tls_backend {
+        #[cfg(feature = "native-tls")]
+        Some(TlsBackend::Native) => builder = builder.use_native_tls(),
+        #[cfg(any(feature = "rustls-webpki-roots", feature = "rustls-native-roots"))]
+        Some(TlsBackend::Rustls) => builder = builder.use_rustls_tls(),
+        None => {}
+    }
+    match &client_override.proxy {
+        Some(ProxyOverride::Direct) => builder = builder.no_proxy(),
+        Some(ProxyOverride::Proxy(proxy)) => builder = builder.proxy(proxy.clone()),
+        None => {
+            for proxy in settings.proxies.iter() {
+                builder = builder.proxy(proxy.clone());
+            }
+        }
+    }
+    if let Some((domain, addrs)) = &client_override.resolve {
+        builder = builder.resolve_to_addrs(domain, addrs);
+    }
+    if let Some(timeout) = client_override.connect_timeout.or(settings.connect_timeout) {
+        builder = builder.connect_timeout(timeout);
+    }
+
+    builder
+        .cookie_provider(Arc::clone(cookie_jar))
+        .redirect(redirect::Policy::custom({
+            let policy = redirect_policy.clone();
+            let guard = redirect_guard.clone();
+            move |a| {
+                let blocked = match (guard.lock(), a.previous().last()) {
+                    (Ok(guard), Some(from)) => guard.check(from, a.url()).err(),
+                    _ => None,
+                };
+                if let Some(blocked) = blocked {
+                    a.error(blocked)
+                } else if let Ok(mut c) = policy.lock() {
+                    c.check(a)
+                } else {
+                    a.stop()
+                }
+            }
+        }))
+        .build()
+}
+
+/// プールから借りたclient、または`CookieClientPool::detached`で作ったプールの外のclient
+pub struct PooledCookieClient(ClientSlot);
+
+enum ClientSlot {
+    Pooled(deadpool::managed::Object<ClientPoolManager>),
+    Detached(CookieClient),
+}
+
+impl Deref for PooledCookieClient {
+    type Target = CookieClient;
+
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            ClientSlot::Pooled(client) => client,
+            ClientSlot::Detached(client) => client,
+        }
+    }
+}
+
+impl DerefMut for PooledCookieClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.0 {
+            ClientSlot::Pooled(client) => client,
+            ClientSlot::Detached(client) => client,
+        }
+    }
+}
+
 pub struct CookieClientPool {
     client_pool: deadpool::managed::Pool<ClientPoolManager>,
 }
 
 impl CookieClientPool {
     pub fn new() -> CookieClientPool {
+        Self::with_proxies(Vec::new())
+    }
+
+    /// 環境変数のプロキシより先に`proxies`が順に試される。
+    pub fn with_proxies(proxies: Vec<reqwest::Proxy>) -> CookieClientPool {
+        Self::with_settings(ClientSettings {
+            proxies,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_settings(settings: ClientSettings) -> CookieClientPool {
+        Self::with_config(settings, &PoolConfig::default())
+    }
+
+    pub fn with_config(settings: ClientSettings, config: &PoolConfig) -> CookieClientPool {
+        let manager = ClientPoolManager {
+            settings: RwLock::new(Arc::new(settings)),
+        };
         Self {
-            client_pool: deadpool::managed::Pool::builder(ClientPoolManager)
+            client_pool: deadpool::managed::Pool::builder(manager)
+                .queue_mode(deadpool::managed::QueueMode::Lifo)
+                .max_size(config.max_size)
+                .wait_timeout(Some(Duration::from_millis(config.wait_timeout_ms)))
+                .runtime(deadpool::Runtime::Tokio1)
                 .build()
                 .unwrap(),
         }
     }
 
-    pub async fn get(
-        &self,
-    ) -> impl std::ops::Deref<Target = CookieClient> + std::ops::DerefMut + Drop {
-        self.client_pool.get().await.unwrap()
+    /// 使用中のclientはそのまま使われ、返された後に捨てられる。
+    pub fn set_connection(&self, proxies: Vec<reqwest::Proxy>, connect_timeout: Option<Duration>) {
+        let mut settings = self.client_pool.manager().settings.write().unwrap();
+        *settings = Arc::new(ClientSettings {
+            proxies,
+            connect_timeout,
+            ..ClientSettings::clone(&settings)
+        });
+    }
+
+    pub fn status(&self) -> deadpool::Status {
+        self.client_pool.status()
+    }
+
+    pub async fn get(&self) -> Result<PooledCookieClient, FetchError> {
+        match self.client_pool.get().await {
+            Ok(client) => Ok(PooledCookieClient(ClientSlot::Pooled(client))),
+            Err(deadpool::managed::PoolError::Backend(e)) => Err(FetchError::Reqwest(e)),
+            Err(_) => Err(FetchError::PoolTimeout),
+        }
+    }
+
+    /// プールの設定で、プールに数えないclientを作る。購読のように使い続けるものに使い、
+    /// プールの全てのclientが塞がって他のリクエストが待たされないようにする。
+    pub async fn detached(&self) -> Result<PooledCookieClient, FetchError> {
+        let client = self
+            .client_pool
+            .manager()
+            .create()
+            .await
+            .map_err(FetchError::Reqwest)?;
+        Ok(PooledCookieClient(ClientSlot::Detached(client)))
     }
 }
@@ -0,0 +1,110 @@
+use super::FetchError;
+use crate::{
+    auth_provider::{AuthChallenge, Credentials},
+    CookieFetchState,
+};
+use base64::Engine;
+use reqwest::header::HeaderValue;
+use std::time::Duration;
+use tauri::Manager;
+
+const AUTH_EVENT: &str = "cookie-fetch://auth";
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthChallengeConfig {
+    /// ユーザーが認証情報を入力する時間を見込み、interceptより長く待つ。
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for AuthChallengeConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    5 * 60 * 1000
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthEvent<'a> {
+    id: u64,
+    #[serde(flatten)]
+    challenge: &'a AuthChallenge,
+}
+
+/// 何も指定しない場合は認証を諦める。
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthReply {
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub authorization: Option<String>,
+}
+
+impl AuthReply {
+    fn into_credentials(self) -> Option<Credentials> {
+        if let Some(v) = self.authorization {
+            return Some(Credentials::Raw(v));
+        }
+
+        Some(Credentials::Basic {
+            username: self.username?,
+            password: self.password.unwrap_or_default(),
+        })
+    }
+}
+
+/// 登録されたproviderに問い合わせ、なければフロントエンドに問い合わせる。
+pub async fn credentials<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    challenge: &AuthChallenge,
+) -> Option<Credentials> {
+    let state = app.state::<CookieFetchState>();
+    if let Some(provider) = &state.registry.auth_provider {
+        if let Some(v) = provider.credentials(challenge).await {
+            return Some(v);
+        }
+    }
+
+    let (id, rx) = state.auth_challenges.insert();
+    if app
+        .emit_all(AUTH_EVENT, AuthEvent { id, challenge })
+        .is_err()
+    {
+        state.auth_challenges.remove(id);
+        return None;
+    }
+
+    let timeout = Duration::from_millis(state.config.auth_challenge.timeout_ms);
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(reply)) => reply.into_credentials(),
+        _ => {
+            state.auth_challenges.remove(id);
+            None
+        }
+    }
+}
+
+pub fn header_value(credentials: &Credentials) -> Result<HeaderValue, FetchError> {
+    let value = match credentials {
+        Credentials::Basic { username, password } => {
+            let token = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password));
+            format!("Basic {}", token)
+        }
+        Credentials::Raw(v) => v.clone(),
+    };
+
+    let mut value = HeaderValue::from_str(&value).map_err(|_| FetchError::InvalidCredentials)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
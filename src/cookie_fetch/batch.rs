@@ -0,0 +1,46 @@
+use super::{fetch, FetchOptions, Response};
+use futures_util::StreamExt;
+
+const DEFAULT_CONCURRENCY: usize = 6;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRequest {
+    pub url: String,
+    #[serde(default)]
+    pub options: Option<FetchOptions>,
+}
+
+/// 1つのリクエストの失敗は他のリクエストに影響しない。
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchResult {
+    Ok(Response),
+    Error { kind: &'static str, message: String },
+}
+
+/// 最大`concurrency`件ずつ並行してfetchし、`requests`の順に結果を返す。
+pub async fn fetch_batch<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    requests: Vec<BatchRequest>,
+    concurrency: Option<usize>,
+) -> Vec<BatchResult> {
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    futures_util::stream::iter(requests)
+        .map(|request| {
+            let app = app.clone();
+            async move {
+                match fetch(app, "fetch_batch", request.url, request.options).await {
+                    Ok(res) => BatchResult::Ok(res),
+                    Err(e) => BatchResult::Error {
+                        kind: e.kind(),
+                        message: e.to_string(),
+                    },
+                }
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await
+}
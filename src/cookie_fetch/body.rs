@@ -0,0 +1,345 @@
+use super::{multipart::MultipartPart, progress::Progress, FetchError};
+use crate::{
+    body_provider::{BodyStream, BoxError},
+    body_transform::RequestTransform,
+    throttle::Bandwidth,
+    CookieFetchState,
+};
+use base64::Engine;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tauri::Manager;
+use tokio::io::{AsyncRead, ReadBuf};
+
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum Body {
+    Bytes(Vec<u8>),
+    Base64(String),
+    File {
+        path: PathBuf,
+        content_type: Option<String>,
+    },
+    Provider(String),
+    Multipart(Vec<MultipartPart>),
+    /// `upload_begin`で得たid。ボディは`upload_chunk`で送られる。
+    Upload(u64),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileBody {
+    path: PathBuf,
+    #[serde(default)]
+    content_type: Option<String>,
+}
+
+impl Body {
+    pub fn new() -> Self {
+        Body::Bytes(Vec::new())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        match self {
+            Body::File { content_type, .. } => content_type.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// ファイルがtauriのfs scopeで許可されているか確認する。
+    pub fn check_scope<R: tauri::Runtime>(
+        &self,
+        app: &tauri::AppHandle<R>,
+    ) -> Result<(), FetchError> {
+        match self {
+            Body::File { path, .. } if !app.fs_scope().is_allowed(path) => {
+                Err(FetchError::FileNotAllowed(path.clone()))
+            }
+            Body::Multipart(parts) => parts.iter().try_for_each(|p| p.body.check_scope(app)),
+            _ => Ok(()),
+        }
+    }
+
+    /// multipartはboundaryをContent-Typeに含める必要があるため`RequestBuilder::multipart`で設定する。
+    /// 変換、`progress`と帯域の制限はストリームにして適用するため、そのボディは再送できない。
+    pub async fn attach<R: tauri::Runtime>(
+        self,
+        app: &tauri::AppHandle<R>,
+        request: reqwest::RequestBuilder,
+        url: &reqwest::Url,
+        progress: &Progress,
+        bandwidth: &Bandwidth,
+    ) -> Result<reqwest::RequestBuilder, FetchError> {
+        let state = app.state::<CookieFetchState>();
+        let transforms: Vec<&Arc<dyn RequestTransform>> = state
+            .registry
+            .request_transforms
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(url.as_str()))
+            .map(|(_, t)| t)
+            .collect();
+
+        match self {
+            Body::Multipart(parts) => {
+                let form =
+                    super::multipart::into_form(parts, app, url, &transforms, progress, bandwidth)
+                        .await?;
+                Ok(request.multipart(form))
+            }
+            body if !transforms.is_empty()
+                || progress.is_enabled()
+                || state.bandwidth.is_upload_limited(bandwidth) =>
+            {
+                // 変換するとボディの長さは変わりうる
+                let len = match &body {
+                    _ if !transforms.is_empty() => None,
+                    Body::Bytes(v) => Some(v.len() as u64),
+                    body @ Body::File { .. } => Some(body.file_len().await?),
+                    _ => None,
+                };
+                let mut headers = reqwest::header::HeaderMap::new();
+                let mut stream = body.into_stream(&state, url).await?;
+                for transform in transforms {
+                    stream = transform.transform(url, &mut headers, stream);
+                }
+                let stream = state.bandwidth.upload(bandwidth, stream);
+                let stream = progress.upload(app, len, stream);
+
+                let mut request = request.headers(headers);
+                if let Some(len) = len {
+                    request = request.header(reqwest::header::CONTENT_LENGTH, len);
+                }
+                Ok(request.body(reqwest::Body::wrap_stream(stream)))
+            }
+            // ストリームのままだとchunkedで送られるため、ファイルの長さを明示する
+            body @ Body::File { .. } => {
+                let len = body.file_len().await?;
+                let request = request.header(reqwest::header::CONTENT_LENGTH, len);
+                Ok(request.body(body.into_reqwest(&state, url).await?))
+            }
+            body => Ok(request.body(body.into_reqwest(&state, url).await?)),
+        }
+    }
+
+    pub async fn file_len(&self) -> Result<u64, FetchError> {
+        match self {
+            Body::File { path, .. } => tokio::fs::metadata(path)
+                .await
+                .map(|m| m.len())
+                .map_err(FetchError::Io),
+            _ => Err(FetchError::UnsupportedBody("non-file")),
+        }
+    }
+
+    pub fn file_name(&self) -> Option<String> {
+        match self {
+            Body::File { path, .. } => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            _ => None,
+        }
+    }
+
+    /// ファイルとproviderはメモリに読み込まずにストリームとして送る。
+    pub async fn into_reqwest(
+        self,
+        state: &CookieFetchState,
+        url: &reqwest::Url,
+    ) -> Result<reqwest::Body, FetchError> {
+        match self {
+            Body::File { path, .. } => match tokio::fs::File::open(path).await {
+                Ok(file) => Ok(reqwest::Body::from(file)),
+                Err(e) => Err(FetchError::Io(e)),
+            },
+            Body::Provider(name) => {
+                let stream = provide(state, &name, url).await?;
+                Ok(reqwest::Body::wrap_stream(stream))
+            }
+            Body::Upload(id) => match state.uploads.take(id) {
+                Some(stream) => Ok(reqwest::Body::wrap_stream(stream)),
+                None => Err(FetchError::InvalidResourceId(id)),
+            },
+            body => body.into_bytes(state, url).await.map(reqwest::Body::from),
+        }
+    }
+
+    pub async fn into_stream(
+        self,
+        state: &CookieFetchState,
+        url: &reqwest::Url,
+    ) -> Result<BodyStream, FetchError> {
+        match self {
+            Body::File { path, .. } => match tokio::fs::File::open(path).await {
+                Ok(file) => Ok(Box::pin(FileStream {
+                    file,
+                    buf: vec![0; FILE_CHUNK_SIZE].into_boxed_slice(),
+                })),
+                Err(e) => Err(FetchError::Io(e)),
+            },
+            Body::Provider(name) => provide(state, &name, url).await,
+            Body::Upload(id) => match state.uploads.take(id) {
+                Some(stream) => Ok(Box::pin(stream)),
+                None => Err(FetchError::InvalidResourceId(id)),
+            },
+            body => {
+                let bytes = Bytes::from(body.into_bytes(state, url).await?);
+                Ok(Box::pin(futures_util::stream::once(
+                    async move { Ok(bytes) },
+                )))
+            }
+        }
+    }
+
+    pub async fn into_bytes(
+        self,
+        state: &CookieFetchState,
+        url: &reqwest::Url,
+    ) -> Result<Vec<u8>, FetchError> {
+        match self {
+            Body::Multipart(_) => Err(FetchError::UnsupportedBody("multipart")),
+            Body::Upload(id) => {
+                let Some(stream) = state.uploads.take(id) else {
+                    return Err(FetchError::InvalidResourceId(id));
+                };
+                stream
+                    .try_fold(Vec::new(), |mut buf, chunk| async move {
+                        buf.extend_from_slice(&chunk);
+                        Ok(buf)
+                    })
+                    .await
+                    .map_err(FetchError::BodyProvider)
+            }
+            Body::Provider(name) => {
+                let stream = provide(state, &name, url).await?;
+                stream
+                    .try_fold(Vec::new(), |mut buf, chunk| async move {
+                        buf.extend_from_slice(&chunk);
+                        Ok(buf)
+                    })
+                    .await
+                    .map_err(FetchError::BodyProvider)
+            }
+            Body::Bytes(v) => Ok(v),
+            Body::Base64(s) => base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map_err(|_| FetchError::InvalidBase64),
+            Body::File { path, .. } => tokio::fs::read(path).await.map_err(FetchError::Io),
+        }
+    }
+}
+
+struct FileStream {
+    file: tokio::fs::File,
+    buf: Box<[u8]>,
+}
+
+impl futures_util::Stream for FileStream {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = ReadBuf::new(&mut this.buf);
+        match Pin::new(&mut this.file).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) if buf.filled().is_empty() => Poll::Ready(None),
+            Poll::Ready(Ok(())) => Poll::Ready(Some(Ok(Bytes::copy_from_slice(buf.filled())))),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(Box::new(e)))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+async fn provide(
+    state: &CookieFetchState,
+    name: &str,
+    url: &reqwest::Url,
+) -> Result<crate::body_provider::BodyStream, FetchError> {
+    let Some(provider) = state.registry.body_providers.get(name) else {
+        return Err(FetchError::UnknownBodyProvider(name.to_string()));
+    };
+
+    provider
+        .provide(url)
+        .await
+        .map_err(FetchError::BodyProvider)
+}
+
+impl<'de> serde::Deserialize<'de> for Body {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Body;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "bytes, `{ base64: string }`, `{ file: { path: string, contentType?: string } }`, `{ provider: string }`, `{ multipart: part[] }`, or `{ upload: number }`",
+                )
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Body::Bytes(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Body::Bytes(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut buf = match seq.size_hint() {
+                    Some(len) => Vec::with_capacity(len),
+                    None => Vec::new(),
+                };
+
+                while let Some(b) = seq.next_element::<u8>()? {
+                    buf.push(b);
+                }
+
+                Ok(Body::Bytes(buf))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let Some(key) = map.next_key::<String>()? else {
+                    return Err(<A::Error as serde::de::Error>::invalid_length(0, &self));
+                };
+
+                match key.as_str() {
+                    "base64" => Ok(Body::Base64(map.next_value()?)),
+                    "file" => {
+                        let FileBody { path, content_type } = map.next_value()?;
+                        Ok(Body::File { path, content_type })
+                    }
+                    "provider" => Ok(Body::Provider(map.next_value()?)),
+                    "multipart" => Ok(Body::Multipart(map.next_value()?)),
+                    "upload" => Ok(Body::Upload(map.next_value()?)),
+                    _ => Err(<A::Error as serde::de::Error>::unknown_field(
+                        &key,
+                        &["base64", "file", "provider", "multipart", "upload"],
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
@@ -0,0 +1,143 @@
+use super::{
+    fetch::{fetch_prepared, prepare_with},
+    Body, FetchError, FetchOptions, Response,
+};
+use crate::CookieFetchState;
+use reqwest::header::HeaderValue;
+use std::collections::HashMap;
+use tauri::Manager;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStep {
+    /// `{{name}}`は前のステップで取り出した値に置き換えられる。
+    pub url: String,
+    /// ヘッダーの値とUTF-8のボディの`{{name}}`も置き換えられる。
+    #[serde(default)]
+    pub options: Option<FetchOptions>,
+    #[serde(default = "HashMap::new")]
+    pub extract: HashMap<String, Extract>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Extract {
+    Header(String),
+    /// JSONのボディに対するJSON Pointer
+    Json(String),
+}
+
+impl Extract {
+    fn apply(&self, res: &Response) -> Option<String> {
+        match self {
+            Extract::Header(name) => res
+                .headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(String::from),
+            Extract::Json(pointer) => {
+                let json: serde_json::Value = serde_json::from_slice(res.body.as_bytes()).ok()?;
+                match json.pointer(pointer)? {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    v => Some(v.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// 全てのステップを同じjarで順に送り、各ステップのレスポンスを返す。失敗したステップで中断する。
+pub async fn fetch_chain<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    steps: Vec<ChainStep>,
+) -> Result<Vec<Response>, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    let mut client = state.client_pool.get().await?;
+    let mut values = HashMap::new();
+    let mut responses = Vec::with_capacity(steps.len());
+
+    for step in steps {
+        let url = substitute(&step.url, &values)?;
+        let mut options = step.options.unwrap_or_default();
+        substitute_options(&mut options, &values)?;
+
+        let prepared = prepare_with(&app, "fetch_chain", client, url, Some(options)).await?;
+        let (returned, result) = fetch_prepared(&app, prepared).await;
+        client = returned;
+        let res = result?;
+
+        for (name, extract) in step.extract.iter() {
+            let Some(value) = extract.apply(&res) else {
+                return Err(FetchError::MissingChainValue(name.clone()));
+            };
+            values.insert(name.clone(), value);
+        }
+        responses.push(res);
+    }
+
+    Ok(responses)
+}
+
+fn substitute_options(
+    options: &mut FetchOptions,
+    values: &HashMap<String, String>,
+) -> Result<(), FetchError> {
+    for value in options.headers.values_mut() {
+        let Ok(s) = value.to_str() else {
+            continue;
+        };
+        if s.contains("{{") {
+            *value = HeaderValue::from_str(&substitute(s, values)?)
+                .map_err(|_| FetchError::InvalidChainValue)?;
+        }
+    }
+
+    if let Body::Bytes(bytes) = &mut options.body {
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            if s.contains("{{") {
+                *bytes = substitute(s, values)?.into_bytes();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `{{name}}`を`values`で置き換える。
+fn substitute(template: &str, values: &HashMap<String, String>) -> Result<String, FetchError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        let Some(value) = values.get(name) else {
+            return Err(FetchError::MissingChainValue(name.to_string()));
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn substitute_values() {
+        let values = HashMap::from([("token".to_string(), "abc".to_string())]);
+        assert_eq!(
+            substitute("csrf={{token}}&user={{ token }}", &values).unwrap(),
+            "csrf=abc&user=abc"
+        );
+        assert_eq!(substitute("no {{ braces", &values).unwrap(), "no {{ braces");
+        assert!(matches!(
+            substitute("{{missing}}", &values),
+            Err(FetchError::MissingChainValue(name)) if name == "missing"
+        ));
+    }
+}
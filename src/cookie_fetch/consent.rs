@@ -0,0 +1,153 @@
+use crate::{snapshot::SnapshotFile, CookieFetchState};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use tauri::Manager;
+
+const CONSENT_FILE_NAME: &str = "cookie-fetch-consent.json";
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentConfig {
+    /// scope外のホストへの最初のリクエストで、送ってよいかをダイアログで確かめる。
+    /// `dialog` featureが必要。
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// scope外のホストごとにユーザーが選んだ結果。変更のたびにファイルへ書き出す。
+#[derive(Debug)]
+pub struct Consent {
+    enabled: bool,
+    file: SnapshotFile,
+    decisions: Mutex<HashMap<String, bool>>,
+    /// 同じホストについて同時に何度も尋ねないよう、ダイアログは1つずつ出す。
+    #[cfg_attr(not(feature = "dialog"), allow(dead_code))]
+    asking: tokio::sync::Mutex<()>,
+}
+
+impl Consent {
+    /// 読み込めないファイルは何も選ばれていないものとして扱う。
+    pub fn load(config: &ConsentConfig, dir: Option<PathBuf>) -> Self {
+        let path = dir.map(|dir| dir.join(CONSENT_FILE_NAME));
+        let decisions = match config.enabled {
+            true => path
+                .as_deref()
+                .and_then(|path| std::fs::read(path).ok())
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .unwrap_or_default(),
+            false => HashMap::new(),
+        };
+
+        Self {
+            enabled: config.enabled,
+            file: SnapshotFile::new(path),
+            decisions: Mutex::new(decisions),
+            asking: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 無効な場合は常に`false`
+    pub fn is_granted(&self, url: &reqwest::Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        self.enabled && self.decision(host) == Some(true)
+    }
+
+    fn decision(&self, host: &str) -> Option<bool> {
+        self.decisions.lock().unwrap().get(host).copied()
+    }
+
+    /// `host`が`None`の場合は全て忘れる。次のリクエストで再び尋ねる。
+    pub fn forget(&self, host: Option<&str>) -> bool {
+        let mut decisions = self.decisions.lock().unwrap();
+        let changed = match host {
+            Some(host) => decisions.remove(host).is_some(),
+            None => {
+                let changed = !decisions.is_empty();
+                decisions.clear();
+                changed
+            }
+        };
+        if changed {
+            self.save(&decisions);
+        }
+        changed
+    }
+
+    #[cfg_attr(not(feature = "dialog"), allow(dead_code))]
+    fn record(&self, host: String, allowed: bool) {
+        let mut decisions = self.decisions.lock().unwrap();
+        decisions.insert(host, allowed);
+        self.save(&decisions);
+    }
+
+    /// 書き込みはバックグラウンドで行う。
+    fn save(&self, decisions: &HashMap<String, bool>) {
+        if let Ok(bytes) = serde_json::to_vec(decisions) {
+            self.file.save(bytes);
+        }
+    }
+}
+
+/// `raw`のホストがscope外でまだ選ばれていなければ、ダイアログで尋ねて結果を覚える。
+/// 許可されたかどうかは続くscopeの検査で判断する。ダイアログを出せなかった場合は覚えない。
+#[cfg(feature = "dialog")]
+pub async fn ask<R: tauri::Runtime>(app: &tauri::AppHandle<R>, command: &str, raw: &str) {
+    let state = app.state::<CookieFetchState>();
+    let consent = &state.consent;
+    if !consent.enabled {
+        return;
+    }
+    let Ok(url) = reqwest::Url::parse(raw) else {
+        return;
+    };
+    let Some(host) = url.host_str() else {
+        return;
+    };
+    let scope = state.config.scope.for_command(command);
+    if scope.is_allowed(&url) || consent.decision(host).is_some() {
+        return;
+    }
+
+    let _asking = consent.asking.lock().await;
+    if consent.decision(host).is_some() {
+        return;
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tauri::api::dialog::ask(
+        None::<&tauri::Window<R>>,
+        &app.package_info().name,
+        format!("Allow this app to contact {}?", host),
+        move |allowed| {
+            let _ = tx.send(allowed);
+        },
+    );
+    if let Ok(allowed) = rx.await {
+        consent.record(host.to_string(), allowed);
+    }
+}
+
+/// `dialog` featureが無効な場合は尋ねない。
+#[cfg(not(feature = "dialog"))]
+pub async fn ask<R: tauri::Runtime>(_app: &tauri::AppHandle<R>, _command: &str, _raw: &str) {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remembers_decisions_per_host() {
+        let consent = Consent::load(&ConsentConfig { enabled: true }, None);
+        let url = |s| reqwest::Url::parse(s).unwrap();
+        consent.record("example.com".to_string(), true);
+        consent.record("tracker.example".to_string(), false);
+
+        assert!(consent.is_granted(&url("https://example.com/a")));
+        assert!(!consent.is_granted(&url("https://tracker.example/")));
+        assert!(!consent.is_granted(&url("https://other.example/")));
+
+        assert!(consent.forget(Some("example.com")));
+        assert!(!consent.is_granted(&url("https://example.com/a")));
+    }
+}
@@ -0,0 +1,159 @@
+use percent_encoding::percent_decode_str;
+
+const MAX_FILENAME_LEN: usize = 255;
+
+/// `Content-Disposition`からファイル名を取り出し、ファイル名として安全な形にする。
+///
+/// `filename*`(RFC 5987)があれば`filename`より優先する。
+/// https://www.rfc-editor.org/rfc/rfc6266#section-4.3
+pub fn suggested_filename(value: &str) -> Option<String> {
+    let mut filename = None;
+    let mut filename_ext = None;
+
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename" => filename = Some(unquote(value.trim())),
+            "filename*" => filename_ext = decode_ext_value(value.trim()),
+            _ => {}
+        }
+    }
+
+    sanitize(&filename_ext.or(filename)?)
+}
+
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+
+    params
+}
+
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+
+    let mut buf = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => buf.extend(chars.next()),
+            c => buf.push(c),
+        }
+    }
+
+    buf
+}
+
+/// `charset'language'value-chars`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+
+    let bytes: Vec<u8> = percent_decode_str(encoded).collect();
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+fn sanitize(filename: &str) -> Option<String> {
+    // パス区切りが含まれていれば最後の要素のみを使う
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+
+    let filename: String = filename
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+        .collect();
+
+    let filename = filename
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' ']);
+
+    if filename.is_empty() {
+        return None;
+    }
+
+    let stem = filename.split('.').next().unwrap_or(filename);
+    let mut filename = if is_reserved_name(stem) {
+        format!("_{}", filename)
+    } else {
+        filename.to_string()
+    };
+
+    if filename.len() > MAX_FILENAME_LEN {
+        let mut end = MAX_FILENAME_LEN;
+        while !filename.is_char_boundary(end) {
+            end -= 1;
+        }
+        filename.truncate(end);
+    }
+
+    Some(filename)
+}
+
+fn is_reserved_name(stem: &str) -> bool {
+    const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+    let upper = stem.to_ascii_uppercase();
+
+    if RESERVED.contains(&upper.as_str()) {
+        return true;
+    }
+
+    match upper
+        .strip_prefix("COM")
+        .or_else(|| upper.strip_prefix("LPT"))
+    {
+        Some(n) => n.len() == 1 && n.as_bytes()[0].is_ascii_digit() && n != "0",
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prefer_ext_value() {
+        let value =
+            r#"attachment; filename="fallback.txt"; filename*=UTF-8''%E2%82%AC%20rates.txt"#;
+
+        assert_eq!(suggested_filename(value).as_deref(), Some("€ rates.txt"));
+    }
+
+    #[test]
+    fn sanitize_quoted_filename() {
+        let value = r#"attachment; filename="..\\..\\evil\"name;.txt""#;
+
+        assert_eq!(suggested_filename(value).as_deref(), Some("evilname;.txt"));
+        assert_eq!(
+            suggested_filename("attachment; filename=con.txt").as_deref(),
+            Some("_con.txt")
+        );
+        assert_eq!(suggested_filename("attachment; filename=\"..\""), None);
+    }
+}
@@ -0,0 +1,34 @@
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentType {
+    #[serde(default)]
+    pub declared: Option<String>,
+    #[serde(default)]
+    pub sniffed: Option<String>,
+}
+
+impl ContentType {
+    pub fn sniff(declared: Option<String>, body: &[u8]) -> Self {
+        Self {
+            declared,
+            sniffed: sniff(body).map(String::from),
+        }
+    }
+}
+
+/// マジックバイトから推定する。判定できないがUTF-8として妥当であれば`text/plain`とする。
+fn sniff(body: &[u8]) -> Option<&'static str> {
+    if body.is_empty() {
+        return None;
+    }
+
+    if let Some(t) = infer::get(body) {
+        return Some(t.mime_type());
+    }
+
+    match std::str::from_utf8(body) {
+        Ok(_) => Some("text/plain"),
+        Err(_) => Some("application/octet-stream"),
+    }
+}
@@ -1,5 +1,5 @@
 #[serde_with::skip_serializing_none]
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CookieProps {
     pub value: String,
@@ -27,6 +27,13 @@ pub struct CookieProps {
     #[serde(default)]
     #[serde(with = "same_site_serde")]
     pub same_site: Option<cookie::SameSite>,
+
+    #[serde(default)]
+    pub partitioned: Option<bool>,
+
+    /// partitioned cookieが属するpartition。`FetchOptions::partition_key`と一致する場合のみ送られる。
+    #[serde(default)]
+    pub partition_key: Option<String>,
 }
 
 mod same_site_serde {
@@ -0,0 +1,195 @@
+/// text/event-streamのパーサ
+/// https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+pub struct EventStreamParser {
+    buf: Vec<u8>,
+    started: bool,
+    data: String,
+    event: String,
+    last_event_id: String,
+    retry: Option<u64>,
+    block_retry: Option<u64>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: String,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+impl EventStreamParser {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            started: false,
+            data: String::new(),
+            event: String::new(),
+            last_event_id: String::new(),
+            retry: None,
+            block_retry: None,
+        }
+    }
+
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    pub fn retry(&self) -> Option<u64> {
+        self.retry
+    }
+
+    /// 再接続時に呼ぶ。last event idとretryは保持する。
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.started = false;
+        self.data.clear();
+        self.event.clear();
+        self.block_retry = None;
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+
+        if !self.started {
+            const BOM: &[u8] = b"\xEF\xBB\xBF";
+            if self.buf.len() < BOM.len() && BOM.starts_with(&self.buf) {
+                return Vec::new();
+            }
+            if self.buf.starts_with(BOM) {
+                self.buf.drain(..BOM.len());
+            }
+            self.started = true;
+        }
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buf.len() {
+            let end = i;
+            match self.buf[i] {
+                b'\n' => i += 1,
+                b'\r' => {
+                    // CRLFがチャンクをまたぐ可能性があるので次のチャンクを待つ
+                    if i + 1 == self.buf.len() {
+                        break;
+                    }
+                    i += if self.buf[i + 1] == b'\n' { 2 } else { 1 };
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+
+            let line = String::from_utf8_lossy(&self.buf[start..end]).into_owned();
+            self.process_line(&line, &mut events);
+            start = i;
+        }
+
+        self.buf.drain(..start);
+        events
+    }
+
+    fn process_line(&mut self, line: &str, events: &mut Vec<SseEvent>) {
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.find(':') {
+            Some(i) => {
+                let value = &line[i + 1..];
+                (&line[..i], value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" => {
+                if !value.contains('\0') {
+                    self.last_event_id = value.to_string();
+                }
+            }
+            "retry" => {
+                if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+                    if let Ok(v) = value.parse() {
+                        self.retry = Some(v);
+                        self.block_retry = Some(v);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        let retry = self.block_retry.take();
+        let event = std::mem::take(&mut self.event);
+        let mut data = std::mem::take(&mut self.data);
+
+        if data.is_empty() {
+            return;
+        }
+
+        if data.ends_with('\n') {
+            data.pop();
+        }
+
+        events.push(SseEvent {
+            id: (!self.last_event_id.is_empty()).then(|| self.last_event_id.clone()),
+            event: if event.is_empty() {
+                String::from("message")
+            } else {
+                event
+            },
+            data,
+            retry,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_events_across_chunks() {
+        let mut parser = EventStreamParser::new();
+
+        let mut events = parser.feed(b"\xEF\xBB\xBFdata: a\r");
+        events.extend(parser.feed(b"\ndata: b\n\n: comment\nid: 7\nevent: up\n"));
+        events.extend(parser.feed(b"data:c\nretry: 100\n\n"));
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: None,
+                    event: String::from("message"),
+                    data: String::from("a\nb"),
+                    retry: None,
+                },
+                SseEvent {
+                    id: Some(String::from("7")),
+                    event: String::from("up"),
+                    data: String::from("c"),
+                    retry: Some(100),
+                },
+            ]
+        );
+        assert_eq!(parser.last_event_id(), "7");
+        assert_eq!(parser.retry(), Some(100));
+    }
+}
@@ -0,0 +1,58 @@
+use super::FetchError;
+use reqwest::Url;
+
+/// `raw`のパスとクエリが、`url`で送られるものと同じか確かめる。
+/// URLのパースでは不正な文字がパーセントエンコードされ、`.`と`..`(`%2e`を含む)のセグメントが解決される。
+/// 既にあるパーセントエンコードは大文字小文字を含めてそのまま残るため、それ以外の変更だけが問題になる。
+/// `Url`は正規化した形しか持てないため、変わる場合に元の形で送る手段はなく、検出だけを行う。
+pub fn check(raw: &str, url: &Url) -> Result<(), FetchError> {
+    let raw = path_and_query(raw);
+    let sent = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    // 空のパスは`/`として送られる
+    let empty_path = raw.is_empty() || raw.starts_with('?');
+    if raw == sent || (empty_path && sent.strip_prefix('/') == Some(raw)) {
+        Ok(())
+    } else {
+        Err(FetchError::UrlNormalized(url.to_string()))
+    }
+}
+
+fn path_and_query(raw: &str) -> &str {
+    let raw = raw.split_once('#').map_or(raw, |(before, _)| before);
+    let Some((_, rest)) = raw.split_once("://") else {
+        return raw;
+    };
+    match rest.find(['/', '?']) {
+        Some(i) => &rest[i..],
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_preserved(raw: &str) -> bool {
+        check(raw, &Url::parse(raw).unwrap()).is_ok()
+    }
+
+    #[test]
+    fn keeps_existing_encoding() {
+        assert!(is_preserved("https://example.com/a%2Fb%7e?sig=abc%3D%3d"));
+        assert!(is_preserved("https://example.com"));
+        assert!(is_preserved("https://example.com?q"));
+        assert!(is_preserved("https://example.com/dir/?q#fragment"));
+    }
+
+    #[test]
+    fn detects_normalization() {
+        assert!(!is_preserved("https://example.com/a b"));
+        assert!(!is_preserved("https://example.com/a/../b"));
+        assert!(!is_preserved("https://example.com/a/%2e/b"));
+        assert!(!is_preserved("https://example.com/?q=\"x\""));
+    }
+}
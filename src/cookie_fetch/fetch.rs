@@ -1,146 +1,665 @@
-use super::{CookieProps, FetchError, FetchOptions, Redirect, Response};
-use crate::{CookieClient, CookieFetchState, RedirectPolicy};
-use reqwest::RequestBuilder;
-use std::collections::HashMap;
+use super::{
+    consent,
+    content_disposition::suggested_filename,
+    exact_url, idn,
+    intercept::intercept,
+    local_url,
+    memory_cache::{CacheKey, ResponseKey},
+    progress::Progress,
+    request_proxy::used_proxy,
+    response_body::response_body,
+    send::{send, SendOptions},
+    timeouts, ContentType, CookieProps, FetchError, FetchOptions, HeaderMap, Payload, PayloadType,
+    Redirect, Response, ThrowOnError,
+};
+use crate::{
+    body_transform::ResponseStream,
+    bridge,
+    cookie_client::{ClientOverride, PooledCookieClient, RedirectGuard},
+    csrf, network,
+    redaction::Redaction,
+    rewrite,
+    scope::ScopeCheck,
+    throttle::Bandwidth,
+    CookieClient, CookieFetchState, RedirectPolicy,
+};
+use bytes::{Bytes, BytesMut};
+use reqwest::{
+    header::{HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE, COOKIE, HOST},
+    RequestBuilder,
+};
+use std::{collections::HashMap, time::Duration};
 use tauri::{Manager, State};
 
+#[derive(Clone)]
+pub struct ResponseOptions {
+    payload_type: PayloadType,
+    strict_decoding: bool,
+    pub lenient_headers: bool,
+    pub throw_on_error: ThrowOnError,
+    sniff_content_type: bool,
+    pub read_timeout: Option<Duration>,
+    pub progress: Progress,
+    pub bandwidth: Bandwidth,
+    /// `Response::proxy`に入れる、使われるプロキシ
+    pub proxy: Option<String>,
+    pub max_body_bytes: Option<u64>,
+    /// `memory_cache`に入れたレスポンスを残す時間。省略した場合は設定の`ttl_ms`
+    pub cache_ttl: Option<Duration>,
+}
+
+impl ResponseOptions {
+    fn cache_key(&self) -> ResponseKey {
+        ResponseKey {
+            payload_type: self.payload_type,
+            strict_decoding: self.strict_decoding,
+            lenient_headers: self.lenient_headers,
+            throw_on_error: match self.throw_on_error {
+                ThrowOnError::Disabled => None,
+                ThrowOnError::Enabled { max_body_length } => Some(max_body_length),
+            },
+            sniff_content_type: self.sniff_content_type,
+            max_body_bytes: self.max_body_bytes,
+        }
+    }
+
+    fn new() -> Self {
+        Self {
+            payload_type: PayloadType::Binary,
+            strict_decoding: false,
+            lenient_headers: false,
+            throw_on_error: ThrowOnError::Disabled,
+            sniff_content_type: false,
+            read_timeout: None,
+            progress: Progress::default(),
+            bandwidth: Bandwidth::default(),
+            proxy: None,
+            max_body_bytes: None,
+            cache_ttl: None,
+        }
+    }
+}
+
+/// `command`は呼び出したコマンドの名前で、使うscopeを決める。
 pub async fn fetch<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
+    command: &str,
     url: String,
     options: Option<FetchOptions>,
 ) -> Result<Response, FetchError> {
-    let url = match reqwest::Url::parse(&url) {
+    if let Some(result) = local_url::fetch_local(&app, &url, options.as_ref()).await {
+        return result;
+    }
+    let prepared = prepare(&app, command, url, options).await?;
+    fetch_prepared(&app, prepared).await.1
+}
+
+/// 続けて同じjarでリクエストできるよう、clientを返す。
+/// 設定の`memory_cache`が有効な場合、キャッシュにあるGETは送らずに返し、ログにも記録しない。
+pub async fn fetch_prepared<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    prepared: Prepared,
+) -> (PooledCookieClient, Result<Response, FetchError>) {
+    let state: State<'_, CookieFetchState> = app.state();
+
+    let request = match prepared.request.build() {
         Ok(v) => v,
-        Err(_) => return Err(FetchError::InvalidUrl),
+        Err(e) => return (prepared.client, Err(FetchError::Reqwest(e))),
     };
+    let cache_key = state
+        .memory_cache
+        .is_enabled()
+        .then(|| {
+            let cookie = prepared.client.cookie_header(request.url());
+            CacheKey::new(
+                &request,
+                cookie.as_ref(),
+                prepared.response_options.cache_key(),
+            )
+        })
+        .flatten();
+    if let Some(res) = cache_key
+        .as_ref()
+        .and_then(|key| state.memory_cache.get(key))
+    {
+        return (prepared.client, Ok(res));
+    }
+    let mut log = state.logger.begin(&request, &prepared.send_options.tags);
+    prepared.client.set_request_id(Some(log.id()));
 
-    let state: State<'_, CookieFetchState> = app.state();
+    let tags = prepared.send_options.tags.clone();
+    let cache_ttl = prepared.response_options.cache_ttl;
+    let result = state
+        .aborts
+        .run(
+            &tags,
+            fetch_core(
+                app,
+                &prepared.client,
+                request,
+                prepared.response_options,
+                prepared.send_options,
+                &state.config.redaction,
+            ),
+        )
+        .await;
 
-    if !state.config.scope.is_allowed(&url) {
-        return Err(FetchError::NotAllowed);
+    match &result {
+        Ok(res) => state
+            .logger
+            .response(&mut log, &res.headers, Some(res.body.as_bytes())),
+        Err(FetchError::Status { headers, body, .. }) => {
+            state.logger.response(&mut log, headers, Some(body))
+        }
+        Err(_) => {}
+    }
+    if let (Some(key), Ok(res)) = (cache_key, &result) {
+        state.memory_cache.insert(key, res, cache_ttl);
     }
 
-    let client = state.client_pool.get().await;
+    let entry = match &result {
+        Ok(res) => log.finish(Some(res.status), Some(res.body.len() as u64), None),
+        Err(e) => log.finish(None, None, Some(e)),
+    };
+    state.logger.write(&entry);
+
+    (prepared.client, result)
+}
+
+pub struct Prepared {
+    pub client: PooledCookieClient,
+    pub request: RequestBuilder,
+    pub response_options: ResponseOptions,
+    pub send_options: SendOptions,
+}
+
+pub async fn prepare<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    command: &str,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Prepared, FetchError> {
+    let state: State<'_, CookieFetchState> = app.state();
+    let client = state.client_pool.get().await?;
+    prepare_with(app, command, client, url, options).await
+}
+
+/// プールの外のclientを使う。購読のように長く送り続けるものに使う。
+pub async fn prepare_detached<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    command: &str,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Prepared, FetchError> {
+    let state: State<'_, CookieFetchState> = app.state();
+    let client = state.client_pool.detached().await?;
+    prepare_with(app, command, client, url, options).await
+}
+
+/// `client`のjarをそのまま使う。リクエストごとの設定は`options`で上書きされる。
+pub async fn prepare_with<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    command: &str,
+    mut client: PooledCookieClient,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Prepared, FetchError> {
+    let state: State<'_, CookieFetchState> = app.state();
+    let runtime = state.runtime_config.current();
+    let options = runtime.apply(options);
+    consent::ask(app, command, &url).await;
+    let mut url = resolve_url(&state, command, &url, options.as_ref())?;
+    let scope = ScopeCheck::new(state.config.scope.for_command(command), &state.consent);
+
+    if let Some(options) = options.as_ref() {
+        override_client(&mut client, &mut url, options).await?;
+    }
+    client.set_partition_key(options.as_ref().and_then(|o| o.partition_key.clone()));
 
     let Some(options) = options else {
-        return fetch_core(&client, client.request(reqwest::Method::GET, url)).await;
+        client.redirect_guard().scope = Some(scope);
+        let mut headers = reqwest::header::HeaderMap::new();
+        intercept(app, &reqwest::Method::GET, &url, &mut headers, &[]).await?;
+        let response_options = ResponseOptions {
+            proxy: used_proxy(&runtime.proxies, None, &url),
+            ..ResponseOptions::new()
+        };
+        let request = client.request(reqwest::Method::GET, url).headers(headers);
+        return Ok(Prepared {
+            client,
+            request,
+            response_options,
+            send_options: SendOptions {
+                command: command.to_string(),
+                ..SendOptions::default()
+            },
+        });
     };
 
-    {
-        let mut cookies_store = client.cookie_store();
-
-        let mut url_buf = reqwest::Url::parse("http://placeholder.example.com").unwrap();
-        for (domain, pairs) in options.cookies {
-            for (name, mut props) in pairs {
-                url_buf
-                    .set_host(Some(&domain))
-                    .map_err(|_| FetchError::InvalidCookieDomain(domain.clone()))?;
-
-                let mut cookie = reqwest_cookie_store::RawCookie::new(name.clone(), props.value);
-
-                if let Some(v) = &props.path {
-                    cookie.set_path(v);
-                }
-
-                if let Some(v) = &props.domain {
-                    cookie.set_domain(v);
-                }
-
-                if let Some(v) = props.http_only.take() {
-                    cookie.set_http_only(v);
-                }
-
-                if let Some(v) = props.secure.take() {
-                    cookie.set_secure(v);
-                }
-
-                cookie.set_max_age(props.max_age.take());
-                cookie.set_expires(props.expires.take());
-                cookie.set_same_site(props.same_site.take());
-
-                cookies_store
-                    .insert_raw(&cookie, &url)
-                    .map_err(|_| FetchError::InvalidCookie {
-                        domain: domain.clone(),
-                        name,
-                    })?;
-            }
-        }
-    }
+    let response_options = ResponseOptions {
+        payload_type: options.payload_type,
+        strict_decoding: options.strict_decoding,
+        lenient_headers: options.lenient_headers,
+        throw_on_error: options.throw_on_error,
+        sniff_content_type: options.sniff_content_type,
+        read_timeout: options.timeouts.read(),
+        progress: Progress::new(options.progress.as_deref()),
+        bandwidth: Bandwidth::new(&options.bandwidth),
+        proxy: used_proxy(&runtime.proxies, options.proxy.as_ref(), &url),
+        max_body_bytes: options.max_body_bytes,
+        cache_ttl: None,
+    };
+
+    insert_cookies(&client, options.cookies, &url)?;
+    client.set_store_response_cookies(options.store_response_cookies);
 
+    let send_options = SendOptions {
+        command: command.to_string(),
+        interactive_redirect: matches!(options.redirect, Redirect::Interactive),
+        auth_challenge: options.auth_challenge,
+        wait_for_network: options.wait_for_network_ms.map(Duration::from_millis),
+        retry_after_max: options.retry_after_max_ms.map(Duration::from_millis),
+        progress: Progress::new(options.progress.as_deref()),
+        tags: options.tags.clone(),
+    };
     {
         let mut redirect_policy = client.redirect_policy();
         match options.redirect {
             Redirect::Follow => *redirect_policy = RedirectPolicy::follow(),
-            Redirect::Manual => *redirect_policy = RedirectPolicy::limited(0),
+            Redirect::Manual | Redirect::Interactive => {
+                *redirect_policy = RedirectPolicy::limited(0)
+            }
             Redirect::Limit { limit } => *redirect_policy = RedirectPolicy::limited(limit),
         }
     }
+    *client.redirect_guard() = RedirectGuard {
+        scope: Some(scope),
+        ..options.redirect_guard
+    };
+
+    options.body.check_scope(app)?;
+
+    let mut headers: reqwest::header::HeaderMap = options.headers.into();
+    csrf::apply(
+        &state.config.csrf,
+        &client,
+        &options.ephemeral_cookies,
+        &url,
+        &mut headers,
+    );
+    bridge::apply_cookie_to_header(
+        &state.config.bridge.cookie_to_header,
+        &client,
+        &options.ephemeral_cookies,
+        &url,
+        &mut headers,
+    );
+    if !options.ephemeral_cookies.is_empty() {
+        merge_cookie_header(&client, &mut headers, options.ephemeral_cookies, &url)?;
+    }
+    if let Some(content_type) = options.body.content_type() {
+        if !headers.contains_key(CONTENT_TYPE) {
+            if let Ok(v) = HeaderValue::from_str(content_type) {
+                headers.insert(CONTENT_TYPE, v);
+            }
+        }
+    }
+
+    if let Some(host) = options.host_override {
+        let host = HeaderValue::from_str(&host).map_err(|_| FetchError::InvalidUrl)?;
+        headers.insert(HOST, host);
+    }
 
-    let builder = client
-        .request(options.method.into(), url)
-        .headers(options.headers.into())
-        .body(options.body);
+    let method: reqwest::Method = options.method.into();
+    intercept(app, &method, &url, &mut headers, &options.tags).await?;
 
-    return fetch_core(&client, builder).await;
+    let mut request = client.request(method, url.clone()).headers(headers);
+    if let Some(timeout) = options.timeouts.total() {
+        request = request.timeout(timeout);
+    }
+    let request = options
+        .body
+        .attach(
+            app,
+            request,
+            &url,
+            &send_options.progress,
+            &response_options.bandwidth,
+        )
+        .await?;
+
+    Ok(Prepared {
+        client,
+        request,
+        response_options,
+        send_options,
+    })
 }
 
-async fn fetch_core(
-    client: &CookieClient,
-    request: RequestBuilder,
-) -> Result<Response, FetchError> {
-    let res = match request.send().await {
+/// `sni_override`、`proxy`と接続のタイムアウトはプールのclientの設定では扱えないため、このリクエスト用のclientを作る。
+/// 接続のタイムアウトだけの場合は、プールのclientごとに作ったものを使い回す。
+async fn override_client(
+    client: &mut CookieClient,
+    url: &mut reqwest::Url,
+    options: &FetchOptions,
+) -> Result<(), FetchError> {
+    let mut client_override = ClientOverride::default();
+
+    // 元のホストへ接続しつつ、ホストをsniに置き換えてTLSのSNIと証明書の検証に用いる
+    if let Some(sni) = &options.sni_override {
+        let addrs = {
+            let url = url.clone();
+            tauri::async_runtime::spawn_blocking(move || url.socket_addrs(|| None))
+                .await
+                .map_err(|e| FetchError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?
+                .map_err(FetchError::Io)?
+        };
+        url.set_host(Some(sni))
+            .map_err(|_| FetchError::InvalidUrl)?;
+        client_override.resolve = Some((sni.clone(), addrs));
+    }
+    if let Some(proxy) = &options.proxy {
+        client_override.proxy = Some(proxy.to_override()?);
+    }
+    client_override.connect_timeout = options.timeouts.connect();
+
+    if client_override.resolve.is_none()
+        && client_override.proxy.is_none()
+        && client_override.connect_timeout.is_none()
+    {
+        client.clear_override();
+        return Ok(());
+    }
+    client
+        .set_override(&client_override)
+        .map_err(FetchError::Reqwest)
+}
+
+pub fn resolve_url(
+    state: &CookieFetchState,
+    command: &str,
+    raw: &str,
+    options: Option<&FetchOptions>,
+) -> Result<reqwest::Url, FetchError> {
+    let mut url = match reqwest::Url::parse(raw) {
         Ok(v) => v,
-        Err(e) => return Err(FetchError::Reqwest(e)),
+        Err(_) => return Err(FetchError::InvalidUrl),
     };
 
-    let cookies: HashMap<String, HashMap<String, CookieProps>> = {
-        let store = client.cookie_store();
-        let mut cookies: HashMap<String, _> = HashMap::new();
+    if let Some(options) = options {
+        if options.exact_url {
+            exact_url::check(raw, &url)?;
+        }
+        if options.reject_idn && idn::is_idn(&url) {
+            return Err(FetchError::IdnRejected(
+                url.host_str().unwrap_or_default().to_string(),
+            ));
+        }
+        options.query.append_to(&mut url);
+    }
+
+    if !is_allowed(state, command, &url) {
+        return Err(FetchError::NotAllowed);
+    }
+
+    rewrite::apply(&state.registry.rewrites, url)
+}
+
+/// `command`のscopeに含まれるか、ダイアログで許可されたホスト
+pub fn is_allowed(state: &CookieFetchState, command: &str, url: &reqwest::Url) -> bool {
+    state.config.scope.for_command(command).is_allowed(url) || state.consent.is_granted(url)
+}
+
+/// Cookieヘッダーを明示するとreqwestはjarのcookieを追加しないため、jarのcookieも合わせて設定する。
+fn merge_cookie_header(
+    client: &CookieClient,
+    headers: &mut reqwest::header::HeaderMap,
+    ephemeral_cookies: HashMap<String, String>,
+    url: &reqwest::Url,
+) -> Result<(), FetchError> {
+    let mut pairs: Vec<String> = Vec::new();
+    if let Some(v) = client.cookie_header(url) {
+        pairs.extend(v.to_str().ok().map(String::from));
+    }
+    for v in headers.get_all(COOKIE) {
+        pairs.extend(v.to_str().ok().map(String::from));
+    }
+    for (name, value) in ephemeral_cookies {
+        let pair = format!("{}={}", name, value);
+        if HeaderValue::from_str(&pair).is_err() {
+            return Err(FetchError::InvalidCookie {
+                domain: url.host_str().unwrap_or_default().to_string(),
+                name,
+            });
+        }
+        pairs.push(pair);
+    }
+
+    // 各要素はHeaderValueとして検証済み
+    let value = HeaderValue::from_str(&pairs.join("; ")).unwrap();
+    headers.insert(COOKIE, value);
+
+    Ok(())
+}
+
+pub fn insert_cookies(
+    client: &CookieClient,
+    cookies: HashMap<String, HashMap<String, CookieProps>>,
+    url: &reqwest::Url,
+) -> Result<(), FetchError> {
+    let partition_key = client.partition_key();
+    let mut cookies_store = client.cookie_store();
 
-        for c in store.iter_any() {
-            let Some(domain) = &c.domain.as_cow() else {
+    let mut url_buf = reqwest::Url::parse("http://placeholder.example.com").unwrap();
+    for (domain, pairs) in cookies {
+        for (name, mut props) in pairs {
+            let partitioned = props.partitioned.unwrap_or(false);
+            if partitioned && partition_key.is_none() {
+                return Err(FetchError::PartitionKeyRequired { domain, name });
+            }
+            if partitioned && props.partition_key != partition_key {
                 continue;
-            };
+            }
 
-            let pairs: &mut HashMap<_, _> = { cookies.entry(domain.to_string()).or_default() };
-
-            pairs.insert(
-                c.name().to_string(),
-                CookieProps {
-                    value: c.value().to_string(),
-                    path: c.path().map(String::from),
-                    domain: c.domain().map(String::from),
-                    http_only: c.http_only(),
-                    secure: c.secure(),
-                    max_age: c.max_age(),
-                    expires: c.expires().and_then(|e| match e {
-                        cookie::Expiration::DateTime(v) => Some(v),
-                        cookie::Expiration::Session => None,
-                    }),
-                    same_site: c.same_site(),
-                },
-            );
+            url_buf
+                .set_host(Some(&domain))
+                .map_err(|_| FetchError::InvalidCookieDomain(domain.clone()))?;
+
+            let mut cookie = reqwest_cookie_store::RawCookie::new(name.clone(), props.value);
+
+            if let Some(v) = &props.path {
+                cookie.set_path(v);
+            }
+
+            if let Some(v) = &props.domain {
+                cookie.set_domain(v);
+            }
+
+            if let Some(v) = props.http_only.take() {
+                cookie.set_http_only(v);
+            }
+
+            if let Some(v) = props.secure.take() {
+                cookie.set_secure(v);
+            }
+
+            cookie.set_max_age(props.max_age.take());
+            cookie.set_expires(props.expires.take());
+            cookie.set_same_site(props.same_site.take());
+
+            cookies_store
+                .insert_raw(&cookie, url)
+                .map_err(|_| FetchError::InvalidCookie {
+                    domain: domain.clone(),
+                    name: name.clone(),
+                })?;
+
+            if partitioned {
+                client.mark_partitioned(domain.clone(), name);
+            }
         }
+    }
 
-        cookies
-    };
+    Ok(())
+}
+
+async fn fetch_core<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    client: &CookieClient,
+    request: reqwest::Request,
+    options: ResponseOptions,
+    send_options: SendOptions,
+    redaction: &Redaction,
+) -> Result<Response, FetchError> {
+    let res = send(app, client, request, send_options).await?;
+
+    let cookies = collect_cookies(client);
 
     let url = res.url().to_string();
+    let unicode_url = idn::unicode_url(res.url());
     let status = res.status().as_u16();
-    let headers = res.headers().clone().into();
-    let body = match res.bytes().await {
-        Ok(v) => v,
-        Err(e) => return Err(FetchError::Reqwest(e)),
-    };
+    let headers: HeaderMap = res.headers().clone().into();
+    if !options.lenient_headers {
+        if let Some(name) = headers.invalid_value() {
+            return Err(FetchError::InvalidHeaderValue(name.to_string()));
+        }
+    }
+    let remote_address = res.remote_addr().map(|a| a.to_string());
+    let local_address = network::local_addr(&res).map(|a| a.to_string());
+    let suggested_filename = res
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(suggested_filename);
+    let state = app.state::<CookieFetchState>();
+    let total = res.content_length();
+    let body = state
+        .bandwidth
+        .download(&options.bandwidth, response_body(&state, res));
+    let body = options.progress.download(app, total, body);
+    let ReadBody {
+        body,
+        received_bytes,
+        truncated,
+    } = read_body(body, options.read_timeout, options.max_body_bytes).await?;
+
+    if let ThrowOnError::Enabled { max_body_length } = options.throw_on_error {
+        if !(200..300).contains(&status) {
+            let body = match max_body_length {
+                Some(max) if body.len() > max => body.slice(..max),
+                _ => body,
+            };
+
+            let mut headers = headers;
+            redaction.apply(&mut headers);
+
+            return Err(FetchError::Status {
+                status,
+                headers,
+                body,
+            });
+        }
+    }
+
+    let content_type = options.sniff_content_type.then(|| {
+        let declared = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        ContentType::sniff(declared, &body)
+    });
+
+    let body = Payload::decode(body, options.payload_type, options.strict_decoding)?;
 
     let res = Response {
         url,
+        unicode_url,
         status,
         headers,
         cookies,
         body,
+        suggested_filename,
+        content_type,
+        content_length: total,
+        received_bytes,
+        truncated,
+        remote_address,
+        local_address,
+        proxy: options.proxy,
     };
 
     Ok(res)
 }
+
+struct ReadBody {
+    body: Bytes,
+    received_bytes: u64,
+    truncated: bool,
+}
+
+/// `max`を超えた時点で読むのをやめる。
+async fn read_body(
+    mut body: ResponseStream,
+    read_timeout: Option<Duration>,
+    max: Option<u64>,
+) -> Result<ReadBody, FetchError> {
+    let mut buf = BytesMut::new();
+    let mut received_bytes = 0;
+    while let Some(chunk) = timeouts::chunk(&mut body, read_timeout).await? {
+        received_bytes += chunk.len() as u64;
+        buf.extend_from_slice(&chunk);
+        if let Some(max) = max.filter(|max| received_bytes > *max) {
+            buf.truncate(max as usize);
+            return Ok(ReadBody {
+                body: buf.freeze(),
+                received_bytes,
+                truncated: true,
+            });
+        }
+    }
+    Ok(ReadBody {
+        body: buf.freeze(),
+        received_bytes,
+        truncated: false,
+    })
+}
+
+pub fn collect_cookies(client: &CookieClient) -> HashMap<String, HashMap<String, CookieProps>> {
+    let store = client.cookie_store();
+    let partition_key = client.partition_key();
+    let mut cookies: HashMap<String, _> = HashMap::new();
+
+    for c in store.iter_any() {
+        let Some(domain) = &c.domain.as_cow() else {
+            continue;
+        };
+
+        let partitioned = client.is_partitioned(domain, c.name());
+        let pairs: &mut HashMap<_, _> = { cookies.entry(domain.to_string()).or_default() };
+
+        pairs.insert(
+            c.name().to_string(),
+            CookieProps {
+                value: c.value().to_string(),
+                path: c.path().map(String::from),
+                domain: c.domain().map(String::from),
+                http_only: c.http_only(),
+                secure: c.secure(),
+                max_age: c.max_age(),
+                expires: c.expires().and_then(|e| match e {
+                    cookie::Expiration::DateTime(v) => Some(v),
+                    cookie::Expiration::Session => None,
+                }),
+                same_site: c.same_site(),
+                partitioned: partitioned.then_some(true),
+                partition_key: if partitioned {
+                    partition_key.clone()
+                } else {
+                    None
+                },
+            },
+        );
+    }
+
+    cookies
+}
@@ -1,25 +1,279 @@
+use super::HeaderMap;
+use bytes::Bytes;
+
+/// Displayに含めるステータスエラーのボディの上限
+const DISPLAY_BODY_LIMIT: usize = 1024;
+
 #[derive(Debug)]
 pub enum FetchError {
     Reqwest(reqwest::Error),
     InvalidCookieDomain(String),
-    InvalidCookie { domain: String, name: String },
+    InvalidCookie {
+        domain: String,
+        name: String,
+    },
+    /// `partition_key`を指定せずに渡したpartitioned cookie
+    PartitionKeyRequired {
+        domain: String,
+        name: String,
+    },
     InvalidUrl,
+    /// `exact_url`で拒否した、パースで変わったURL
+    UrlNormalized(String),
+    /// `reject_idn`で拒否したホスト
+    IdnRejected(String),
+    InvalidMethod(String),
+    InvalidProxy(crate::body_provider::BoxError),
+    InvalidText {
+        offset: usize,
+    },
+    InvalidBase64,
+    InvalidDataUrl,
+    /// `lenient_headers`無しで受け取った、可視ASCIIでないヘッダーの値
+    InvalidHeaderValue(String),
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    SocketClosed,
+    GrpcWeb(&'static str),
+    InvalidResourceId(u64),
+    FileNotAllowed(std::path::PathBuf),
+    Io(std::io::Error),
+    UnknownBodyProvider(String),
+    UnknownPreset(String),
+    MissingChainValue(String),
+    InvalidChainValue,
+    BodyProvider(crate::body_provider::BoxError),
+    BodyTransform(crate::body_provider::BoxError),
+    RequestSigner(crate::body_provider::BoxError),
+    UnsupportedBody(&'static str),
+    Cancelled,
+    Aborted,
+    TooManyRedirects,
+    InvalidCredentials,
+    ProxyAuthRequired,
+    NetworkUnreachable,
+    /// `Builder::fault`で起こした接続の失敗
+    InjectedFault,
+    ReadTimeout,
+    /// 206以外のステータス
+    RangeNotSatisfied(u16),
+    InvalidContentRange,
+    /// `redirectGuard`に拒否されたリダイレクト先
+    RedirectBlocked(String),
+    Status {
+        status: u16,
+        headers: HeaderMap,
+        body: Bytes,
+    },
     NotAllowed,
+    /// `allow_set_config`無しで呼ばれた`set_config`
+    ConfigLocked,
+    /// `pool.waitTimeoutMs`の間にプールのclientが空かなかった
+    PoolTimeout,
+    /// 設定の`memory_cache`が無効な場合の`prefetch`
+    CacheDisabled,
+}
+
+impl FetchError {
+    /// 接続や転送の失敗で、送り直せば成功しうるもの。設定やscopeによる拒否は含まない。
+    pub fn is_transport(&self) -> bool {
+        match self {
+            FetchError::Reqwest(e) => !e.is_builder() && !e.is_redirect(),
+            FetchError::NetworkUnreachable
+            | FetchError::InjectedFault
+            | FetchError::ReadTimeout => true,
+            _ => false,
+        }
+    }
+
+    /// ログに記録するエラーの種類
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::Reqwest(e) if e.is_timeout() => "timeout",
+            FetchError::Reqwest(e) if e.is_connect() => "connect",
+            FetchError::Reqwest(_) => "reqwest",
+            FetchError::InvalidCookieDomain(_) => "invalidCookieDomain",
+            FetchError::InvalidCookie { .. } => "invalidCookie",
+            FetchError::PartitionKeyRequired { .. } => "partitionKeyRequired",
+            FetchError::InvalidUrl => "invalidUrl",
+            FetchError::UrlNormalized(_) => "urlNormalized",
+            FetchError::IdnRejected(_) => "idnRejected",
+            FetchError::InvalidMethod(_) => "invalidMethod",
+            FetchError::InvalidProxy(_) => "invalidProxy",
+            FetchError::InvalidText { .. } => "invalidText",
+            FetchError::InvalidBase64 => "invalidBase64",
+            FetchError::InvalidDataUrl => "invalidDataUrl",
+            FetchError::InvalidHeaderValue(_) => "invalidHeaderValue",
+            FetchError::WebSocket(_) => "webSocket",
+            FetchError::SocketClosed => "socketClosed",
+            FetchError::GrpcWeb(_) => "grpcWeb",
+            FetchError::InvalidResourceId(_) => "invalidResourceId",
+            FetchError::FileNotAllowed(_) => "fileNotAllowed",
+            FetchError::Io(_) => "io",
+            FetchError::UnknownBodyProvider(_) => "unknownBodyProvider",
+            FetchError::UnknownPreset(_) => "unknownPreset",
+            FetchError::MissingChainValue(_) => "missingChainValue",
+            FetchError::InvalidChainValue => "invalidChainValue",
+            FetchError::BodyProvider(_) => "bodyProvider",
+            FetchError::BodyTransform(_) => "bodyTransform",
+            FetchError::RequestSigner(_) => "requestSigner",
+            FetchError::UnsupportedBody(_) => "unsupportedBody",
+            FetchError::Cancelled => "cancelled",
+            FetchError::Aborted => "aborted",
+            FetchError::TooManyRedirects => "tooManyRedirects",
+            FetchError::InvalidCredentials => "invalidCredentials",
+            FetchError::ProxyAuthRequired => "proxyAuthRequired",
+            FetchError::NetworkUnreachable => "networkUnreachable",
+            FetchError::InjectedFault => "connect",
+            FetchError::ReadTimeout => "readTimeout",
+            FetchError::RangeNotSatisfied(_) => "rangeNotSatisfied",
+            FetchError::InvalidContentRange => "invalidContentRange",
+            FetchError::RedirectBlocked(_) => "redirectBlocked",
+            FetchError::Status { .. } => "status",
+            FetchError::NotAllowed => "notAllowed",
+            FetchError::ConfigLocked => "configLocked",
+            FetchError::PoolTimeout => "poolTimeout",
+            FetchError::CacheDisabled => "cacheDisabled",
+        }
+    }
 }
 
 impl std::fmt::Display for FetchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FetchError::NotAllowed => f.write_str("url not allowed on the configured scope"),
+            FetchError::ConfigLocked => {
+                f.write_str("set_config is not allowed by the plugin config")
+            }
+            FetchError::PoolTimeout => f.write_str("timed out waiting for a client from the pool"),
+            FetchError::CacheDisabled => {
+                f.write_str("memory_cache is not enabled in the plugin config")
+            }
             FetchError::InvalidCookieDomain(domain) => {
                 write!(f, "invalid cookie domain `{}`", domain)
             }
             FetchError::InvalidCookie { domain, name } => {
                 write!(f, "invalid cookie `{}` of domain `{}`", name, domain)
             }
+            FetchError::PartitionKeyRequired { domain, name } => write!(
+                f,
+                "partitioned cookie `{}` of domain `{}` requires a partitionKey",
+                name, domain
+            ),
             FetchError::InvalidUrl => f.write_str("invalid url"),
+            FetchError::UrlNormalized(url) => write!(f, "url would be sent as `{}`", url),
+            FetchError::IdnRejected(host) => {
+                write!(f, "internationalized domain name `{}` rejected", host)
+            }
+            FetchError::InvalidMethod(method) => write!(f, "invalid method `{}`", method),
+            FetchError::InvalidProxy(e) => write!(f, "invalid proxy: {}", e),
+            FetchError::Status { status, body, .. } => {
+                write!(f, "request failed with status {}", status)?;
+                if !body.is_empty() {
+                    let shown = &body[..body.len().min(DISPLAY_BODY_LIMIT)];
+                    write!(f, ": {}", String::from_utf8_lossy(shown))?;
+                    if shown.len() < body.len() {
+                        f.write_str("…")?;
+                    }
+                }
+                Ok(())
+            }
+            FetchError::WebSocket(e) => <_ as std::fmt::Display>::fmt(e, f),
+            FetchError::SocketClosed => f.write_str("socket is not open"),
+            FetchError::GrpcWeb(e) => f.write_str(e),
+            FetchError::InvalidResourceId(rid) => write!(f, "invalid resource id `{}`", rid),
+            FetchError::FileNotAllowed(path) => {
+                write!(f, "file `{}` not allowed on the fs scope", path.display())
+            }
+            FetchError::Io(e) => <_ as std::fmt::Display>::fmt(e, f),
+            FetchError::UnknownBodyProvider(name) => {
+                write!(f, "unknown body provider `{}`", name)
+            }
+            FetchError::UnknownPreset(name) => write!(f, "unknown preset `{}`", name),
+            FetchError::MissingChainValue(name) => write!(f, "chain value `{}` is missing", name),
+            FetchError::InvalidChainValue => f.write_str("chain value is not a valid header value"),
+            FetchError::BodyProvider(e) => <_ as std::fmt::Display>::fmt(e, f),
+            FetchError::BodyTransform(e) => <_ as std::fmt::Display>::fmt(e, f),
+            FetchError::RequestSigner(e) => write!(f, "failed to sign request: {}", e),
+            FetchError::UnsupportedBody(kind) => write!(f, "{} body cannot be used here", kind),
+            FetchError::Cancelled => f.write_str("request cancelled by interceptor"),
+            FetchError::Aborted => f.write_str("request aborted by tag"),
+            FetchError::TooManyRedirects => f.write_str("too many redirects"),
+            FetchError::InvalidCredentials => {
+                f.write_str("credentials are not a valid header value")
+            }
+            FetchError::ProxyAuthRequired => f.write_str("proxy authentication required"),
+            FetchError::NetworkUnreachable => f.write_str("network is unreachable"),
+            FetchError::InjectedFault => f.write_str("injected connection failure"),
+            FetchError::ReadTimeout => f.write_str("timed out waiting for the response body"),
+            FetchError::RangeNotSatisfied(status) => {
+                write!(f, "range request answered with status {}", status)
+            }
+            FetchError::InvalidContentRange => {
+                f.write_str("content-range does not match the requested range")
+            }
+            FetchError::RedirectBlocked(url) => write!(f, "redirect to `{}` blocked", url),
+            FetchError::InvalidBase64 => f.write_str("invalid base64 body"),
+            FetchError::InvalidDataUrl => f.write_str("invalid data url"),
+            FetchError::InvalidHeaderValue(name) => {
+                write!(f, "response header `{}` is not visible ascii", name)
+            }
+            FetchError::InvalidText { offset } => {
+                write!(f, "invalid utf-8 sequence at byte offset {}", offset)
+            }
             FetchError::Reqwest(e) => <_ as std::fmt::Display>::fmt(e, f),
         }
     }
 }
 impl std::error::Error for FetchError {}
+
+/// JSに渡すエラー。`BinIpcError`はDisplayの文字列しか渡さないため、JSONの文字列にする。
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    kind: &'static str,
+    message: String,
+    status: Option<u16>,
+    headers: Option<HeaderMap>,
+    body: Option<Vec<u8>>,
+    /// `invalidText`の不正なバイトの位置
+    offset: Option<usize>,
+}
+
+impl From<FetchError> for ErrorReport {
+    fn from(e: FetchError) -> Self {
+        let mut report = Self {
+            kind: e.kind(),
+            message: e.to_string(),
+            status: None,
+            headers: None,
+            body: None,
+            offset: None,
+        };
+        match e {
+            FetchError::Status {
+                status,
+                headers,
+                body,
+            } => {
+                report.status = Some(status);
+                report.headers = Some(headers);
+                report.body = Some(body.to_vec());
+            }
+            FetchError::RangeNotSatisfied(status) => report.status = Some(status),
+            FetchError::InvalidText { offset } => report.offset = Some(offset),
+            _ => {}
+        }
+        report
+    }
+}
+
+impl std::fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_string(self) {
+            Ok(json) => f.write_str(&json),
+            Err(_) => f.write_str(&self.message),
+        }
+    }
+}
+impl std::error::Error for ErrorReport {}
@@ -1,4 +1,9 @@
-use super::{cookie_props::CookieProps, headermap::HeaderMap, method::Method, redirect::Redirect};
+use super::{
+    body::Body, cookie_props::CookieProps, headermap::HeaderMap, method::Method,
+    payload_type::PayloadType, query::Query, redirect::Redirect, request_proxy::RequestProxy,
+    throw_on_error::ThrowOnError, timeouts::Timeouts,
+};
+use crate::{throttle::BandwidthConfig, RedirectGuard};
 use std::collections::HashMap;
 
 #[derive(Debug, serde::Deserialize)]
@@ -6,14 +11,116 @@ use std::collections::HashMap;
 pub struct FetchOptions {
     #[serde(default = "default_method")]
     pub method: Method,
+    #[serde(default = "Query::new")]
+    pub query: Query,
+    /// URLのパースでパスかクエリが変わる場合、送らずにエラーにする。`query`で追加するものは含まない。
+    /// 変更を検出するだけで、reqwestはパースしたURLしか送れないため元の形のまま送ることはできない。
+    #[serde(default)]
+    pub exact_url: bool,
+    /// 国際化ドメイン名のホストへは送らずにエラーにする。
+    #[serde(default)]
+    pub reject_idn: bool,
     #[serde(default = "HeaderMap::new")]
     pub headers: HeaderMap,
     #[serde(default = "HashMap::new")]
     pub cookies: HashMap<String, HashMap<String, CookieProps>>,
+    /// jarに保存せず、このリクエストのCookieヘッダーにのみ追加する。リダイレクト先には送られない。
+    #[serde(default = "HashMap::new")]
+    pub ephemeral_cookies: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub store_response_cookies: bool,
     #[serde(default = "default_redirect_policy")]
     pub redirect: Redirect,
-    #[serde(default = "Vec::new")]
-    pub body: Vec<u8>,
+    #[serde(default)]
+    pub redirect_guard: RedirectGuard,
+    #[serde(default = "Body::new")]
+    pub body: Body,
+    #[serde(default = "default_payload_type")]
+    pub payload_type: PayloadType,
+    #[serde(default)]
+    pub strict_decoding: bool,
+    /// 可視ASCIIでないレスポンスヘッダーの値を、エラーにせず置き換えた文字列で返す。
+    #[serde(default)]
+    pub lenient_headers: bool,
+    #[serde(default = "ThrowOnError::new")]
+    pub throw_on_error: ThrowOnError,
+    #[serde(default)]
+    pub sniff_content_type: bool,
+    /// partitioned cookieのpartition key。埋め込み元のトップレベルサイト(例: `https://example.com`)を渡す。
+    /// `cookies`にpartitioned cookieを含める場合は必須。
+    #[serde(default)]
+    pub partition_key: Option<String>,
+    /// 401/407に対して認証情報を問い合わせ、一度だけ再送する。
+    #[serde(default)]
+    pub auth_challenge: bool,
+    /// URLのホストの代わりに送るHostヘッダー
+    #[serde(default)]
+    pub host_override: Option<String>,
+    /// URLのホストへ接続し、TLSのSNIとHostにはこのホスト名を用いる。
+    #[serde(default)]
+    pub sni_override: Option<String>,
+    /// 設定のプロキシの代わりに使うプロキシ。`"direct"`の場合はプロキシを使わない。
+    #[serde(default)]
+    pub proxy: Option<RequestProxy>,
+    /// 経路がない場合に、接続できるようになるまで待つ最大の時間
+    #[serde(default)]
+    pub wait_for_network_ms: Option<u64>,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    /// 429と、Retry-Afterのある503に対して、Retry-Afterの秒数を合計でこの時間まで待って再送する。
+    #[serde(default)]
+    pub retry_after_max_ms: Option<u64>,
+    /// 経過を`cookie-fetch://progress/{progress}`に送る。
+    #[serde(default)]
+    pub progress: Option<String>,
+    /// ログ、イベントと`pool_status`の集計に付けるラベル
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 設定の`bandwidth`に加えて適用する、このリクエストの帯域の上限
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    /// これより長いボディは切り詰め、`Response::truncated`を立てる。
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            method: default_method(),
+            query: Query::new(),
+            exact_url: false,
+            reject_idn: false,
+            headers: HeaderMap::new(),
+            cookies: HashMap::new(),
+            ephemeral_cookies: HashMap::new(),
+            store_response_cookies: true,
+            redirect: default_redirect_policy(),
+            redirect_guard: RedirectGuard::default(),
+            body: Body::new(),
+            payload_type: default_payload_type(),
+            strict_decoding: false,
+            lenient_headers: false,
+            throw_on_error: ThrowOnError::new(),
+            sniff_content_type: false,
+            partition_key: None,
+            auth_challenge: false,
+            host_override: None,
+            sni_override: None,
+            proxy: None,
+            wait_for_network_ms: None,
+            timeouts: Timeouts::default(),
+            retry_after_max_ms: None,
+            progress: None,
+            tags: Vec::new(),
+            bandwidth: BandwidthConfig::default(),
+            max_body_bytes: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 fn default_redirect_policy() -> Redirect {
@@ -23,3 +130,7 @@ fn default_redirect_policy() -> Redirect {
 fn default_method() -> Method {
     Method::GET
 }
+
+fn default_payload_type() -> PayloadType {
+    PayloadType::Binary
+}
@@ -0,0 +1,170 @@
+use super::{
+    fetch::{collect_cookies, prepare, Prepared, ResponseOptions},
+    grpc_web_frame::{self, Deframer, Frame},
+    response_body::response_body,
+    send::{send, SendOptions},
+    timeouts, Body, CookieProps, ErrorReport, FetchError, FetchOptions, HeaderMap,
+};
+use crate::{cookie_client::PooledCookieClient, subscriptions, CookieFetchState};
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use std::collections::HashMap;
+use tauri::Manager;
+
+const CONTENT_TYPE_GRPC_WEB: &str = "application/grpc-web+proto";
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum GrpcWebMessage {
+    Head {
+        status: u16,
+        headers: HeaderMap,
+        cookies: HashMap<String, HashMap<String, CookieProps>>,
+    },
+    Message {
+        data: Vec<u8>,
+    },
+    /// trailers-onlyのレスポンスではヘッダーのgrpc-statusを使う。
+    #[serde(rename_all = "camelCase")]
+    Trailers {
+        trailers: HashMap<String, String>,
+        grpc_status: Option<u32>,
+        grpc_message: Option<String>,
+    },
+    Error(ErrorReport),
+    Close,
+}
+
+/// unaryとserver-streamingの呼び出しに対応する。メッセージは受け取るたびに
+/// `cookie-fetch://grpc-web/<channel>`に送る。`maxBodyBytes`を超えた場合はそこで止める。
+pub async fn grpc_web<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    message: Body,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), FetchError> {
+    message.check_scope(&app)?;
+    let Prepared {
+        client,
+        request,
+        response_options,
+        send_options,
+    } = prepare(&app, "grpc_web", url, options).await?;
+
+    let mut request = match request.build() {
+        Ok(v) => v,
+        Err(e) => return Err(FetchError::Reqwest(e)),
+    };
+    let state: tauri::State<'_, CookieFetchState> = app.state();
+    let message = message.into_bytes(&state, request.url()).await?;
+    *request.method_mut() = reqwest::Method::POST;
+    let headers = request.headers_mut();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(CONTENT_TYPE_GRPC_WEB),
+    );
+    headers.insert(ACCEPT, HeaderValue::from_static(CONTENT_TYPE_GRPC_WEB));
+    headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+    *request.body_mut() = Some(grpc_web_frame::encode(&message).into());
+
+    let event = format!("cookie-fetch://grpc-web/{}", channel);
+    let tags = send_options.tags.clone();
+    subscriptions::spawn(&app, channel, {
+        let app = app.clone();
+        async move {
+            let state = app.state::<CookieFetchState>();
+            let result = run(
+                &app,
+                &event,
+                client,
+                request,
+                response_options,
+                send_options,
+            );
+            if let Err(e) = state.aborts.run(&tags, result).await {
+                let _ = app.emit_all(&event, GrpcWebMessage::Error(e.into()));
+            }
+            let _ = app.emit_all(&event, GrpcWebMessage::Close);
+        }
+    });
+
+    Ok(())
+}
+
+async fn run<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event: &str,
+    client: PooledCookieClient,
+    request: reqwest::Request,
+    response_options: ResponseOptions,
+    send_options: SendOptions,
+) -> Result<(), FetchError> {
+    let emit = |message: GrpcWebMessage| {
+        let _ = app.emit_all(event, message);
+    };
+
+    let res = send(app, &client, request, send_options).await?;
+
+    let headers: HeaderMap = res.headers().clone().into();
+    if !response_options.lenient_headers {
+        if let Some(name) = headers.invalid_value() {
+            return Err(FetchError::InvalidHeaderValue(name.to_string()));
+        }
+    }
+    emit(GrpcWebMessage::Head {
+        status: res.status().as_u16(),
+        headers: headers.clone(),
+        cookies: collect_cookies(&client),
+    });
+
+    let state = app.state::<CookieFetchState>();
+    let total = res.content_length();
+    let body = state
+        .bandwidth
+        .download(&response_options.bandwidth, response_body(&state, res));
+    let mut body = response_options.progress.download(app, total, body);
+
+    let mut deframer = Deframer::new();
+    let mut trailers = HashMap::new();
+    let mut received_bytes = 0;
+    while let Some(chunk) = timeouts::chunk(&mut body, response_options.read_timeout).await? {
+        received_bytes += chunk.len() as u64;
+        if response_options
+            .max_body_bytes
+            .is_some_and(|max| received_bytes > max)
+        {
+            return Err(FetchError::GrpcWeb("response exceeds maxBodyBytes"));
+        }
+
+        for frame in deframer.feed(&chunk).map_err(FetchError::GrpcWeb)? {
+            match frame {
+                Frame::Message(m) => emit(GrpcWebMessage::Message { data: m.to_vec() }),
+                Frame::Trailers(t) => trailers.extend(t),
+            }
+        }
+    }
+
+    if !deframer.is_empty() {
+        return Err(FetchError::GrpcWeb("incomplete grpc-web frame"));
+    }
+
+    let lookup = |name: &str| {
+        trailers.get(name).cloned().or_else(|| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        })
+    };
+    let grpc_status = lookup("grpc-status").and_then(|v| v.parse().ok());
+    let grpc_message =
+        lookup("grpc-message").map(|v| percent_decode_str(&v).decode_utf8_lossy().into_owned());
+
+    emit(GrpcWebMessage::Trailers {
+        trailers,
+        grpc_status,
+        grpc_message,
+    });
+    Ok(())
+}
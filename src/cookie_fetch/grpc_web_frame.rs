@@ -0,0 +1,108 @@
+use bytes::{Buf, Bytes, BytesMut};
+
+const FLAG_TRAILERS: u8 = 0x80;
+const FLAG_COMPRESSED: u8 = 0x01;
+const HEADER_LEN: usize = 5;
+
+/// gRPC-Webのフレーム
+/// https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Message(Bytes),
+    Trailers(Vec<(String, String)>),
+}
+
+pub fn encode(message: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + message.len());
+    buf.push(0);
+    buf.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    buf.extend_from_slice(message);
+    buf
+}
+
+pub struct Deframer {
+    buf: BytesMut,
+}
+
+impl Deframer {
+    pub fn new() -> Self {
+        Self {
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// 途中までしか受信していないフレームが残っていれば`false`。
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Frame>, &'static str> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut frames = Vec::new();
+        while self.buf.len() >= HEADER_LEN {
+            let flag = self.buf[0];
+            let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]);
+            let len = len as usize;
+
+            if self.buf.len() < HEADER_LEN + len {
+                break;
+            }
+
+            if flag & FLAG_COMPRESSED != 0 {
+                return Err("compressed grpc-web frames are not supported");
+            }
+
+            self.buf.advance(HEADER_LEN);
+            let data = self.buf.split_to(len).freeze();
+
+            frames.push(if flag & FLAG_TRAILERS != 0 {
+                Frame::Trailers(parse_trailers(&data))
+            } else {
+                Frame::Message(data)
+            });
+        }
+
+        Ok(frames)
+    }
+}
+
+fn parse_trailers(data: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(data)
+        .split("\r\n")
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some((key.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deframe_split_chunks() {
+        let mut encoded = encode(b"hello");
+        encoded.push(FLAG_TRAILERS);
+        let trailers = b"grpc-status: 0\r\nGrpc-Message: ok\r\n";
+        encoded.extend_from_slice(&(trailers.len() as u32).to_be_bytes());
+        encoded.extend_from_slice(trailers);
+
+        let mut deframer = Deframer::new();
+        let mut frames = deframer.feed(&encoded[..3]).unwrap();
+        frames.extend(deframer.feed(&encoded[3..]).unwrap());
+
+        assert!(deframer.is_empty());
+        assert_eq!(
+            frames,
+            vec![
+                Frame::Message(Bytes::from_static(b"hello")),
+                Frame::Trailers(vec![
+                    (String::from("grpc-status"), String::from("0")),
+                    (String::from("grpc-message"), String::from("ok")),
+                ]),
+            ]
+        );
+    }
+}
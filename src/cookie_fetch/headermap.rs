@@ -8,7 +8,7 @@ use std::{
     str::FromStr,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct HeaderMap(reqwest::header::HeaderMap);
 
 impl Deref for HeaderMap {
@@ -32,6 +32,22 @@ impl HeaderMap {
     pub fn with_capacity(capacity: usize) -> Self {
         Self(reqwest::header::HeaderMap::with_capacity(capacity))
     }
+
+    /// 可視ASCIIでない値を持つ最初のヘッダー
+    pub fn invalid_value(&self) -> Option<&HeaderName> {
+        self.0
+            .iter()
+            .find(|(_, value)| value.to_str().is_err())
+            .map(|(name, _)| name)
+    }
+}
+
+/// 不正なUTF-8はU+FFFDに置き換え、タブ以外の制御文字は取り除く。
+fn sanitize(value: &HeaderValue) -> String {
+    String::from_utf8_lossy(value.as_bytes())
+        .chars()
+        .filter(|c| *c == '\t' || !c.is_control())
+        .collect()
 }
 
 impl Into<reqwest::header::HeaderMap> for HeaderMap {
@@ -73,7 +89,8 @@ impl<'a> serde::Serialize for GetAllWrapper<'a> {
         for val in self.0.iter() {
             match val.to_str() {
                 Ok(s) => seq.serialize_element(s)?,
-                Err(e) => return Err(<S::Error as serde::ser::Error>::custom(e.to_string())),
+                // レスポンスの値は`FetchOptions::lenient_headers`が無い場合に検査済み
+                Err(_) => seq.serialize_element(&sanitize(val))?,
             }
         }
         seq.end()
@@ -195,4 +212,10 @@ mod test {
 
         assert_eq!(&result, r#"{"k":["v0","v1","v2"]}"#);
     }
+
+    #[test]
+    fn sanitize_invalid_values() {
+        let value = HeaderValue::from_bytes(b"caf\xc3\xa9\xc2\x85 \xff").unwrap();
+        assert_eq!(sanitize(&value), "caf\u{e9} \u{fffd}");
+    }
 }
@@ -0,0 +1,119 @@
+use super::{
+    fetch::{prepare_detached, Prepared},
+    send::send,
+    FetchError, FetchOptions,
+};
+use crate::{subscriptions, CookieFetchState};
+use std::time::Duration;
+use tauri::Manager;
+
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatOptions {
+    pub interval_ms: u64,
+    /// 省略した場合は2xxを成功とする。
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum HeartbeatMessage {
+    /// 成功していたものが失敗し始めた。ログアウトされた可能性がある。
+    Failing {
+        status: Option<u16>,
+        message: String,
+    },
+    Recovered,
+    /// `abort_by_tag`で止められた。
+    Close,
+}
+
+/// `interval_ms`(最短1秒)ごとに`url`へリクエストを送り続ける。`unsubscribe`で止める。
+/// 同じjarを使い続けるため、レスポンスのcookieで更新されたセッションが次のリクエストで送られる。
+/// 失敗し始めたときと回復したときに`cookie-fetch://heartbeat/{channel}`を送る。
+/// 続けて使うjarはプールの外のclientに持たせる。
+pub async fn heartbeat<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    heartbeat: HeartbeatOptions,
+    channel: String,
+) -> Result<(), FetchError> {
+    let prepared = prepare_detached(&app, "heartbeat", url, options).await?;
+    // ボディを送るたびに作り直せるか、ここで確かめておく
+    if prepared.request.try_clone().is_none() {
+        return Err(FetchError::UnsupportedBody("streamed"));
+    }
+    let event = format!("cookie-fetch://heartbeat/{}", channel);
+
+    let tags = prepared.send_options.tags.clone();
+    subscriptions::spawn(&app, channel, {
+        let app = app.clone();
+        async move {
+            let state = app.state::<CookieFetchState>();
+            let task = run(&app, &event, prepared, heartbeat);
+            if state.aborts.run(&tags, task).await.is_err() {
+                let _ = app.emit_all(&event, HeartbeatMessage::Close);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// `abort_by_tag`で中断されるまで返らない。
+async fn run<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event: &str,
+    prepared: Prepared,
+    heartbeat: HeartbeatOptions,
+) -> Result<(), FetchError> {
+    let Prepared {
+        client,
+        request,
+        send_options,
+        ..
+    } = prepared;
+    let mut interval =
+        tokio::time::interval(Duration::from_millis(heartbeat.interval_ms).max(MIN_INTERVAL));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // 最初のtickはすぐに終わる
+    interval.tick().await;
+
+    let mut failing = false;
+    loop {
+        interval.tick().await;
+
+        let result = match request.try_clone().map(|r| r.build()) {
+            Some(Ok(request)) => send(app, &client, request, send_options.clone()).await,
+            Some(Err(e)) => Err(FetchError::Reqwest(e)),
+            None => Err(FetchError::UnsupportedBody("streamed")),
+        };
+        let failure = match result {
+            Ok(res) => {
+                let status = res.status().as_u16();
+                let ok = match heartbeat.expected_status {
+                    Some(expected) => status == expected,
+                    None => res.status().is_success(),
+                };
+                (!ok).then(|| (Some(status), format!("unexpected status {}", status)))
+            }
+            Err(e) => Some((None, e.to_string())),
+        };
+
+        match failure {
+            Some((status, message)) if !failing => {
+                failing = true;
+                let _ = app.emit_all(event, HeartbeatMessage::Failing { status, message });
+            }
+            None if failing => {
+                failing = false;
+                let _ = app.emit_all(event, HeartbeatMessage::Recovered);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use reqwest::Url;
+
+/// ホストを国際化ドメイン名として含むか。URLのパースで常にpunycodeに変換されるため、`xn--`のラベルで判断する。
+pub fn is_idn(url: &Url) -> bool {
+    url.domain().is_some_and(|domain| {
+        domain.split('.').any(|label| {
+            label
+                .get(..4)
+                .is_some_and(|p| p.eq_ignore_ascii_case("xn--"))
+        })
+    })
+}
+
+/// ホストをUnicodeに戻したURL。国際化ドメイン名でない場合は`None`。
+pub fn unicode_url(url: &Url) -> Option<String> {
+    if !is_idn(url) {
+        return None;
+    }
+    let domain = url.domain()?;
+    let (unicode, result) = idna::domain_to_unicode(domain);
+    result.ok()?;
+
+    let s = url.as_str();
+    let mut start = url.scheme().len() + "://".len();
+    if !url.username().is_empty() || url.password().is_some() {
+        start += s[start..].find('@')? + 1;
+    }
+    let end = start + domain.len();
+    Some(format!("{}{}{}", &s[..start], unicode, &s[end..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_host_back_to_unicode() {
+        let url = Url::parse("https://user:pw@日本語.example/パス?q").unwrap();
+        assert_eq!(url.host_str(), Some("xn--wgv71a119e.example"));
+        assert_eq!(
+            unicode_url(&url).as_deref(),
+            Some("https://user:pw@日本語.example/%E3%83%91%E3%82%B9?q")
+        );
+    }
+
+    #[test]
+    fn ascii_hosts_are_not_idn() {
+        let url = Url::parse("https://example.com/").unwrap();
+        assert!(!is_idn(&url));
+        assert_eq!(unicode_url(&url), None);
+    }
+}
@@ -0,0 +1,170 @@
+use super::{consent, retry, sign::sign, FetchError, HeaderMap};
+use crate::{CookieClient, CookieFetchState};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION},
+    Method, StatusCode,
+};
+use std::time::Duration;
+use tauri::Manager;
+
+const REDIRECT_EVENT: &str = "cookie-fetch://redirect";
+const MAX_REDIRECTS: usize = 20;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InteractiveRedirectConfig {
+    /// 返信を待つ時間。過ぎた場合は3xxのレスポンスを返す。
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for InteractiveRedirectConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    60 * 1000
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RedirectEvent<'a> {
+    id: u64,
+    status: u16,
+    url: &'a str,
+    location: &'a str,
+    headers: &'a HeaderMap,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedirectReply {
+    #[serde(default)]
+    pub follow: bool,
+    /// 指定した場合は`Location`の代わりにこのURLへ進む。
+    #[serde(default)]
+    pub location: Option<String>,
+    /// 次のリクエストで同じ名前のヘッダーを置き換える。
+    #[serde(default = "HeaderMap::new")]
+    pub headers: HeaderMap,
+}
+
+/// `redirect: "interactive"`の場合、リダイレクトのたびに`cookie-fetch://redirect`を送り、
+/// `redirect_reply`の応答に従う。応答がない場合やfollowしない場合は3xxのレスポンスを返す。
+/// 進む先がscope外なら、`command`のscopeで検査する前に同意を尋ねる。
+/// 署名は送るたびに行い、次のリクエストには署名する前のヘッダーを使う。
+pub async fn send<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    command: &str,
+    client: &CookieClient,
+    mut request: reqwest::Request,
+    interactive: bool,
+) -> Result<reqwest::Response, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    if !interactive {
+        sign(&state, &mut request).await?;
+        return retry::execute(&state.config.retry, client, request)
+            .await
+            .map_err(FetchError::Reqwest);
+    }
+
+    for _ in 0..MAX_REDIRECTS {
+        let method = request.method().clone();
+        let headers = request.headers().clone();
+        let retry = request.try_clone();
+        sign(&state, &mut request).await?;
+
+        let res = retry::execute(&state.config.retry, client, request)
+            .await
+            .map_err(FetchError::Reqwest)?;
+        let status = res.status();
+        if !status.is_redirection() {
+            return Ok(res);
+        }
+        let Some(location) = res
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| res.url().join(v).ok())
+        else {
+            return Ok(res);
+        };
+
+        let (id, rx) = state.redirects.insert();
+        let mut res_headers = HeaderMap::from(res.headers().clone());
+        state.config.redaction.apply_cookies(&mut res_headers);
+        let event = RedirectEvent {
+            id,
+            status: status.as_u16(),
+            url: res.url().as_str(),
+            location: location.as_str(),
+            headers: &res_headers,
+        };
+        if app.emit_all(REDIRECT_EVENT, event).is_err() {
+            state.redirects.remove(id);
+            return Ok(res);
+        }
+
+        let timeout = Duration::from_millis(state.config.interactive_redirect.timeout_ms);
+        let reply = match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(v)) if v.follow => v,
+            _ => {
+                state.redirects.remove(id);
+                return Ok(res);
+            }
+        };
+
+        let next_url = match reply.location {
+            Some(v) => reqwest::Url::parse(&v).map_err(|_| FetchError::InvalidUrl)?,
+            None => location,
+        };
+        consent::ask(app, command, next_url.as_str()).await;
+        client
+            .redirect_guard()
+            .check(res.url(), &next_url)
+            .map_err(|e| FetchError::RedirectBlocked(e.0.to_string()))?;
+        let cross_origin = res.url().origin() != next_url.origin();
+
+        // 303と、POSTに対する301/302はGETに変わりボディを送らない
+        let next_method = match status {
+            StatusCode::SEE_OTHER if method != Method::HEAD => Method::GET,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND if method == Method::POST => {
+                Method::GET
+            }
+            _ => method.clone(),
+        };
+
+        let mut next = if next_method == method {
+            retry.ok_or(FetchError::UnsupportedBody("streaming"))?
+        } else {
+            let mut next = reqwest::Request::new(next_method, next_url.clone());
+            *next.headers_mut() = headers;
+            next.headers_mut().remove(CONTENT_TYPE);
+            next.headers_mut().remove(CONTENT_LENGTH);
+            next
+        };
+
+        *next.url_mut() = next_url;
+        // cookieはjarから付け直させる
+        next.headers_mut().remove(COOKIE);
+        if cross_origin {
+            next.headers_mut().remove(AUTHORIZATION);
+        }
+
+        let reply_headers: reqwest::header::HeaderMap = reply.headers.into();
+        for name in reply_headers.keys() {
+            next.headers_mut().remove(name);
+        }
+        for (name, value) in reply_headers.iter() {
+            next.headers_mut().append(name, value.clone());
+        }
+
+        request = next;
+    }
+
+    Err(FetchError::TooManyRedirects)
+}
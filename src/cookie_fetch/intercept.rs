@@ -0,0 +1,101 @@
+use super::{FetchError, HeaderMap};
+use crate::CookieFetchState;
+use std::time::Duration;
+use tauri::Manager;
+
+const INTERCEPT_EVENT: &str = "cookie-fetch://intercept";
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterceptConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for InterceptConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InterceptRequest<'a> {
+    id: u64,
+    method: &'a str,
+    url: &'a str,
+    headers: &'a HeaderMap,
+    tags: &'a [String],
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterceptReply {
+    /// 同じ名前のヘッダーは置き換えられる。
+    #[serde(default = "HeaderMap::new")]
+    pub headers: HeaderMap,
+    #[serde(default)]
+    pub cancel: bool,
+}
+
+/// 送信前にリクエストをフロントエンドに渡し、`intercept_reply`で返された変更を適用する。
+/// タイムアウトした場合はそのまま送る。
+pub async fn intercept<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    method: &reqwest::Method,
+    url: &reqwest::Url,
+    headers: &mut reqwest::header::HeaderMap,
+    tags: &[String],
+) -> Result<(), FetchError> {
+    let state = app.state::<CookieFetchState>();
+    let config = &state.config.intercept;
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let (id, rx) = state.interceptors.insert();
+    let mut snapshot = HeaderMap::from(headers.clone());
+    state.config.redaction.apply_cookies(&mut snapshot);
+    let request = InterceptRequest {
+        id,
+        method: method.as_str(),
+        url: url.as_str(),
+        headers: &snapshot,
+        tags,
+    };
+    if app.emit_all(INTERCEPT_EVENT, request).is_err() {
+        state.interceptors.remove(id);
+        return Ok(());
+    }
+
+    let reply = match tokio::time::timeout(Duration::from_millis(config.timeout_ms), rx).await {
+        Ok(Ok(v)) => v,
+        _ => {
+            state.interceptors.remove(id);
+            return Ok(());
+        }
+    };
+
+    if reply.cancel {
+        return Err(FetchError::Cancelled);
+    }
+
+    let reply_headers: reqwest::header::HeaderMap = reply.headers.into();
+    for name in reply_headers.keys() {
+        headers.remove(name);
+    }
+    for (name, value) in reply_headers.iter() {
+        headers.append(name, value.clone());
+    }
+
+    Ok(())
+}
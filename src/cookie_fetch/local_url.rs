@@ -0,0 +1,241 @@
+use super::{
+    ContentType, FetchError, FetchOptions, HeaderMap, Payload, PayloadType, Response, ThrowOnError,
+};
+use crate::CookieFetchState;
+use base64::Engine;
+use bytes::Bytes;
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use std::{collections::HashMap, io::Read, path::PathBuf};
+use tauri::Manager;
+
+const DEFAULT_DATA_TYPE: &str = "text/plain;charset=US-ASCII";
+/// `convertFileSrc`が返すURLの前置き。Windowsでは`https://asset.localhost/`になる。
+const ASSET_PREFIXES: [&str; 2] = ["asset://localhost/", "https://asset.localhost/"];
+
+/// 読んだボディ。`len`は切り詰める前の長さ
+struct LocalBody {
+    status: u16,
+    content_type: Option<String>,
+    body: Bytes,
+    len: u64,
+}
+
+/// `data:`とアセットのURLは送らずに読み、同じ`Response`として返す。それ以外は`None`。
+/// アセットはtauriのasset protocolのscopeで許可されたファイルに限り、無いファイルは404になる。
+/// `maxBodyBytes`を超える分は読まず、`throwOnError`は404に対して働く。
+pub async fn fetch_local<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    url: &str,
+    options: Option<&FetchOptions>,
+) -> Option<Result<Response, FetchError>> {
+    let max = options.and_then(|o| o.max_body_bytes).or(app
+        .state::<CookieFetchState>()
+        .runtime_config
+        .current()
+        .max_body_bytes);
+
+    let body = if let Some(data) = strip_prefix_ignore_case(url, "data:") {
+        match parse_data_url(data) {
+            Ok((content_type, mut body)) => {
+                let len = body.len() as u64;
+                if let Some(max) = max.filter(|max| len > *max) {
+                    body.truncate(max as usize);
+                }
+                LocalBody {
+                    status: 200,
+                    content_type: Some(content_type),
+                    body,
+                    len,
+                }
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    } else if let Some(path) = ASSET_PREFIXES.iter().find_map(|p| url.strip_prefix(p)) {
+        match read_asset(app, path, max).await {
+            Ok(body) => body,
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        return None;
+    };
+
+    Some(respond(url, body, options))
+}
+
+fn respond(
+    url: &str,
+    local: LocalBody,
+    options: Option<&FetchOptions>,
+) -> Result<Response, FetchError> {
+    let payload_type = options.map_or(PayloadType::Binary, |o| o.payload_type);
+    let strict_decoding = options.is_some_and(|o| o.strict_decoding);
+    let sniff_content_type = options.is_some_and(|o| o.sniff_content_type);
+    let throw_on_error = options.map_or(ThrowOnError::Disabled, |o| o.throw_on_error);
+    let LocalBody {
+        status,
+        content_type,
+        body,
+        len,
+    } = local;
+
+    let mut headers = HeaderMap::new();
+    if let Some(v) = content_type
+        .as_deref()
+        .and_then(|t| HeaderValue::from_str(t).ok())
+    {
+        headers.insert(CONTENT_TYPE, v);
+    }
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+
+    if let ThrowOnError::Enabled { max_body_length } = throw_on_error {
+        if !(200..300).contains(&status) {
+            let body = match max_body_length {
+                Some(max) if body.len() > max => body.slice(..max),
+                _ => body,
+            };
+            return Err(FetchError::Status {
+                status,
+                headers,
+                body,
+            });
+        }
+    }
+
+    let content_type = sniff_content_type.then(|| ContentType::sniff(content_type.clone(), &body));
+    let truncated = (body.len() as u64) < len;
+
+    Ok(Response {
+        url: url.to_string(),
+        unicode_url: None,
+        status,
+        headers,
+        cookies: HashMap::new(),
+        body: Payload::decode(body, payload_type, strict_decoding)?,
+        suggested_filename: None,
+        content_type,
+        content_length: Some(len),
+        received_bytes: len,
+        truncated,
+        remote_address: None,
+        local_address: None,
+        proxy: None,
+    })
+}
+
+/// `data:`より後ろを読み、メディアタイプとデータを返す。
+fn parse_data_url(data: &str) -> Result<(String, Bytes), FetchError> {
+    let Some((meta, data)) = data.split_once(',') else {
+        return Err(FetchError::InvalidDataUrl);
+    };
+    // フラグメントはデータに含まない
+    let data = data.split_once('#').map_or(data, |(data, _)| data);
+
+    let (media_type, base64) = match meta.rsplit_once(';') {
+        Some((media_type, param)) if param.trim().eq_ignore_ascii_case("base64") => {
+            (media_type, true)
+        }
+        _ => (meta, false),
+    };
+    let media_type = media_type.trim();
+    let media_type = if media_type.is_empty() || media_type.starts_with(';') {
+        format!("text/plain{}", media_type)
+    } else {
+        media_type.to_string()
+    };
+    let media_type = match media_type.as_str() {
+        "text/plain" => DEFAULT_DATA_TYPE.to_string(),
+        _ => media_type,
+    };
+
+    let bytes: Vec<u8> = percent_decode_str(data).collect();
+    let body = if base64 {
+        let bytes: Vec<u8> = bytes
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(bytes)
+            .map_err(|_| FetchError::InvalidDataUrl)?
+    } else {
+        bytes
+    };
+
+    Ok((media_type, Bytes::from(body)))
+}
+
+async fn read_asset<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    path: &str,
+    max: Option<u64>,
+) -> Result<LocalBody, FetchError> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = PathBuf::from(percent_decode_str(path).decode_utf8_lossy().into_owned());
+    if !app.asset_protocol_scope().is_allowed(&path) {
+        return Err(FetchError::FileNotAllowed(path));
+    }
+
+    let read = tauri::async_runtime::spawn_blocking(move || {
+        let file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut body = Vec::new();
+        file.take(max.unwrap_or(u64::MAX)).read_to_end(&mut body)?;
+        Ok::<_, std::io::Error>((Bytes::from(body), len))
+    })
+    .await
+    .map_err(|e| FetchError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+
+    match read {
+        Ok((body, len)) => Ok(LocalBody {
+            status: 200,
+            content_type: None,
+            body,
+            len,
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LocalBody {
+            status: 404,
+            content_type: None,
+            body: Bytes::new(),
+            len: 0,
+        }),
+        Err(e) => Err(FetchError::Io(e)),
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(p) if p.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plain_data_url() {
+        let (media_type, body) = parse_data_url(",Hello%2C%20World!").unwrap();
+        assert_eq!(media_type, DEFAULT_DATA_TYPE);
+        assert_eq!(&body[..], b"Hello, World!");
+    }
+
+    #[test]
+    fn base64_data_url() {
+        let (media_type, body) = parse_data_url("image/png;base64,iVBO Rw==").unwrap();
+        assert_eq!(media_type, "image/png");
+        assert_eq!(&body[..], b"\x89PNG");
+    }
+
+    #[test]
+    fn charset_without_type() {
+        let (media_type, _) = parse_data_url(";charset=utf-8,x").unwrap();
+        assert_eq!(media_type, "text/plain;charset=utf-8");
+    }
+
+    #[test]
+    fn invalid_data_urls() {
+        assert!(parse_data_url("text/plain").is_err());
+        assert!(parse_data_url(";base64,!!!").is_err());
+    }
+}
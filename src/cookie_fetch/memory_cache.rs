@@ -0,0 +1,275 @@
+use super::{PayloadType, Response};
+use reqwest::header::HeaderValue;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryCacheConfig {
+    /// 0の場合はキャッシュしない。
+    #[serde(default)]
+    pub max_entries: usize,
+    #[serde(default = "default_ttl_ms")]
+    pub ttl_ms: u64,
+}
+
+impl Default for MemoryCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 0,
+            ttl_ms: default_ttl_ms(),
+        }
+    }
+}
+
+fn default_ttl_ms() -> u64 {
+    1000
+}
+
+/// レスポンスの読み方の設定。同じレスポンスでも、これが違えば返る値が違う。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResponseKey {
+    pub payload_type: PayloadType,
+    pub strict_decoding: bool,
+    pub lenient_headers: bool,
+    /// 無効な場合は`None`
+    pub throw_on_error: Option<Option<usize>>,
+    pub sniff_content_type: bool,
+    pub max_body_bytes: Option<u64>,
+}
+
+/// ボディのないGETのURL、ヘッダー、jarから送るcookieとレスポンスの読み方
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    url: String,
+    headers: Vec<(String, Vec<u8>)>,
+    /// jarのcookieは送信時に加わるため、リクエストのヘッダーには含まれない。
+    cookie: Option<Vec<u8>>,
+    response: ResponseKey,
+}
+
+impl CacheKey {
+    pub fn new(
+        request: &reqwest::Request,
+        cookie: Option<&HeaderValue>,
+        response: ResponseKey,
+    ) -> Option<Self> {
+        if request.method() != reqwest::Method::GET || request.body().is_some() {
+            return None;
+        }
+
+        let mut headers: Vec<_> = request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+            .collect();
+        headers.sort();
+
+        Some(Self {
+            url: request.url().to_string(),
+            headers,
+            cookie: cookie.map(|v| v.as_bytes().to_vec()),
+            response,
+        })
+    }
+}
+
+struct Entry {
+    response: Response,
+    expires: Instant,
+    last_used: u64,
+}
+
+struct Entries {
+    map: HashMap<CacheKey, Entry>,
+    clock: u64,
+}
+
+/// 同じリクエストが短い間に繰り返された場合に、ネットワークに出ずに同じレスポンスを返す。
+/// 件数が`max_entries`を超えると、期限切れのもの、次に最も長く使われていないものから捨てる。
+pub struct MemoryCache {
+    max_entries: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    /// `prefetch`を1つずつ送るためのロック
+    prefetching: tokio::sync::Mutex<()>,
+}
+
+impl MemoryCache {
+    pub fn new(config: &MemoryCacheConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            ttl: Duration::from_millis(config.ttl_ms),
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                clock: 0,
+            }),
+            prefetching: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub async fn prefetching(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.prefetching.lock().await
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries > 0 && !self.ttl.is_zero()
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<Response> {
+        self.get_at(key, Instant::now())
+    }
+
+    /// 2xxで、切り詰められていないものだけ保存する。`ttl`を省略した場合は設定の`ttl_ms`の間残す。
+    pub fn insert(&self, key: CacheKey, response: &Response, ttl: Option<Duration>) {
+        if (200..300).contains(&response.status) && !response.truncated {
+            let ttl = ttl.unwrap_or(self.ttl);
+            self.insert_at(key, response.clone(), Instant::now(), ttl);
+        }
+    }
+
+    fn get_at(&self, key: &CacheKey, now: Instant) -> Option<Response> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+
+        match entries.map.get_mut(key) {
+            Some(entry) if entry.expires > now => {
+                entry.last_used = clock;
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                entries.map.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert_at(&self, key: CacheKey, response: Response, now: Instant, ttl: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.max_entries {
+            entries.map.retain(|_, entry| entry.expires > now);
+        }
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.max_entries {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.map.remove(&oldest);
+            }
+        }
+
+        entries.map.insert(
+            key,
+            Entry {
+                response,
+                expires: now + ttl,
+                last_used: clock,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response_key() -> ResponseKey {
+        ResponseKey {
+            payload_type: PayloadType::Binary,
+            strict_decoding: false,
+            lenient_headers: false,
+            throw_on_error: None,
+            sniff_content_type: false,
+            max_body_bytes: None,
+        }
+    }
+
+    fn key(url: &str) -> CacheKey {
+        CacheKey {
+            url: url.to_string(),
+            headers: Vec::new(),
+            cookie: None,
+            response: response_key(),
+        }
+    }
+
+    fn response(url: &str) -> Response {
+        serde_json::from_value(serde_json::json!({
+            "url": url,
+            "status": 200,
+            "headers": {},
+            "cookies": {},
+            "body": "",
+        }))
+        .unwrap()
+    }
+
+    fn cache(max_entries: usize) -> MemoryCache {
+        MemoryCache::new(&MemoryCacheConfig {
+            max_entries,
+            ttl_ms: 1000,
+        })
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache(2);
+        let now = Instant::now();
+        cache.insert_at(key("a"), response("a"), now, cache.ttl);
+        cache.insert_at(key("b"), response("b"), now, cache.ttl);
+        assert!(cache.get_at(&key("a"), now).is_some());
+
+        cache.insert_at(key("c"), response("c"), now, cache.ttl);
+        assert!(cache.get_at(&key("a"), now).is_some());
+        assert!(cache.get_at(&key("b"), now).is_none());
+        assert!(cache.get_at(&key("c"), now).is_some());
+    }
+
+    #[test]
+    fn keys_differ_by_cookies_and_limits() {
+        let request = || {
+            reqwest::Request::new(
+                reqwest::Method::GET,
+                reqwest::Url::parse("https://example.com/me").unwrap(),
+            )
+        };
+        let alice = HeaderValue::from_static("session=alice");
+        let bob = HeaderValue::from_static("session=bob");
+
+        let a = CacheKey::new(&request(), Some(&alice), response_key());
+        let b = CacheKey::new(&request(), Some(&bob), response_key());
+        assert!(a.is_some());
+        assert_ne!(a, b);
+        assert_ne!(a, CacheKey::new(&request(), None, response_key()));
+
+        let limited = ResponseKey {
+            max_body_bytes: Some(1024),
+            ..response_key()
+        };
+        assert_ne!(a, CacheKey::new(&request(), Some(&alice), limited));
+    }
+
+    #[test]
+    fn expires_after_ttl() {
+        let cache = cache(2);
+        let now = Instant::now();
+        cache.insert_at(key("a"), response("a"), now, cache.ttl);
+        assert!(cache
+            .get_at(&key("a"), now + Duration::from_millis(999))
+            .is_some());
+        assert!(cache
+            .get_at(&key("a"), now + Duration::from_millis(1000))
+            .is_none());
+    }
+}
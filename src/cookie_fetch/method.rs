@@ -79,3 +79,15 @@ impl<'de> serde::Deserialize<'de> for Method {
         deserializer.deserialize_str(Visitor)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extension_methods() {
+        let method: Method = serde_json::from_str("\"PROPFIND\"").unwrap();
+        assert_eq!(method.as_str(), "PROPFIND");
+        assert!(serde_json::from_str::<Method>("\"NOT A TOKEN\"").is_err());
+    }
+}
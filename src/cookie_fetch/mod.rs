@@ -1,17 +1,88 @@
+mod auth;
+mod batch;
+mod body;
+mod chain;
+mod consent;
+mod content_disposition;
+mod content_type;
 mod cookie_props;
+mod event_stream;
+mod exact_url;
 mod fetch;
 mod fetch_error;
 mod fetch_options;
+mod grpc_web;
+mod grpc_web_frame;
 mod headermap;
+mod heartbeat;
+mod idn;
+mod interactive_redirect;
+mod intercept;
+mod local_url;
+mod memory_cache;
 mod method;
+mod multipart;
+mod ndjson;
+mod outbox;
+mod payload;
+mod payload_type;
+mod preconnect;
+mod prefetch;
+mod preset;
+mod probe;
+mod progress;
+mod query;
+mod range;
+mod reader;
 mod redirect;
+mod request_proxy;
 mod response;
+mod response_body;
+mod retry;
+mod runtime_config;
+mod schedule;
+mod send;
+mod sign;
+mod sse;
+mod throw_on_error;
+mod timeouts;
+mod upload;
+mod ws;
 
+use content_type::ContentType;
 use cookie_props::CookieProps;
 use headermap::HeaderMap;
+use payload_type::PayloadType;
+use query::Query;
 use redirect::Redirect;
+use throw_on_error::ThrowOnError;
 
+pub use auth::{AuthChallengeConfig, AuthReply};
+pub use batch::{fetch_batch, BatchRequest, BatchResult};
+pub use body::Body;
+pub use chain::{fetch_chain, ChainStep};
+pub use consent::{Consent, ConsentConfig};
 pub use fetch::fetch;
-pub use fetch_error::FetchError;
+pub use fetch_error::{ErrorReport, FetchError};
 pub use fetch_options::FetchOptions;
+pub use grpc_web::grpc_web;
+pub use heartbeat::{heartbeat, HeartbeatOptions};
+pub use interactive_redirect::{InteractiveRedirectConfig, RedirectReply};
+pub use intercept::{InterceptConfig, InterceptReply};
+pub use memory_cache::{MemoryCache, MemoryCacheConfig};
+pub use ndjson::ndjson;
+pub use outbox::{flush_outbox, Outbox, OutboxItem, OutboxRequest};
+pub use payload::Payload;
+pub use preconnect::preconnect;
+pub use prefetch::{prefetch, PrefetchOptions};
+pub use preset::fetch_preset;
+pub use probe::{probe, Probe};
+pub use range::{fetch_range, RangeResponse};
+pub use reader::{close_body, fetch_begin, read_chunk, ResponseHead};
 pub use response::Response;
+pub use retry::RetryConfig;
+pub use runtime_config::{ConfigPatch, EffectiveConfig, RuntimeConfig, RuntimeSettings};
+pub use schedule::{schedule, ScheduleOptions};
+pub use sse::sse;
+pub use upload::{upload_chunk, upload_end};
+pub use ws::{ws_close, ws_connect, ws_send};
@@ -0,0 +1,84 @@
+use super::{progress::Progress, Body, FetchError, HeaderMap};
+use crate::{body_transform::RequestTransform, throttle::Bandwidth, CookieFetchState};
+use reqwest::multipart::{Form, Part};
+use std::sync::Arc;
+use tauri::Manager;
+
+/// multipartの1つのpart。`headers`はContent-Disposition、Content-Typeの後にそのまま書き込まれる。
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipartPart {
+    pub name: String,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default = "HeaderMap::new")]
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+/// partは与えられた順に送られる。`transforms`はpartごとに適用し、変換が入れたヘッダーはpartのヘッダーになる。
+/// `progress`には全てのpartのボディを合わせた量を送る。boundaryとpartのヘッダーは数えない。
+/// 帯域の制限はpartのボディに適用する。
+pub async fn into_form<R: tauri::Runtime>(
+    parts: Vec<MultipartPart>,
+    app: &tauri::AppHandle<R>,
+    url: &reqwest::Url,
+    transforms: &[&Arc<dyn RequestTransform>],
+    progress: &Progress,
+    bandwidth: &Bandwidth,
+) -> Result<Form, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    let streamed = !transforms.is_empty()
+        || progress.is_enabled()
+        || state.bandwidth.is_upload_limited(bandwidth);
+
+    // 変換するとボディの長さは変わりうる
+    let mut lens = Vec::with_capacity(parts.len());
+    for part in parts.iter() {
+        lens.push(match &part.body {
+            _ if !transforms.is_empty() => None,
+            Body::Bytes(v) => Some(v.len() as u64),
+            body @ Body::File { .. } => Some(body.file_len().await?),
+            _ => None,
+        });
+    }
+    let upload = progress.upload_parts(app, lens.iter().copied().sum(), parts.len());
+
+    let mut form = Form::new();
+    for (part, len) in parts.into_iter().zip(lens) {
+        let content_type = part
+            .content_type
+            .or_else(|| part.body.content_type().map(String::from));
+
+        let filename = part.filename.or_else(|| part.body.file_name());
+        let mut headers: reqwest::header::HeaderMap = part.headers.into();
+        let body = match part.body {
+            body if streamed => {
+                let mut stream = body.into_stream(&state, url).await?;
+                for transform in transforms {
+                    stream = transform.transform(url, &mut headers, stream);
+                }
+                let stream = state.bandwidth.upload(bandwidth, stream);
+                reqwest::Body::wrap_stream(upload.part(stream))
+            }
+            body => body.into_reqwest(&state, url).await?,
+        };
+        // ストリームの長さはわからないため、わかっている場合は明示する
+        let mut p = match len {
+            Some(len) => Part::stream_with_length(body, len),
+            None => Part::stream(body),
+        };
+        if let Some(v) = filename {
+            p = p.file_name(v);
+        }
+        if let Some(v) = content_type {
+            p = p.mime_str(&v).map_err(FetchError::Reqwest)?;
+        }
+
+        form = form.part(part.name, p.headers(headers));
+    }
+
+    Ok(form)
+}
@@ -0,0 +1,160 @@
+use super::{
+    fetch::{prepare, Prepared},
+    response_body::response_body,
+    send::send,
+    FetchError, FetchOptions, HeaderMap, ThrowOnError,
+};
+use crate::{subscriptions, CookieFetchState};
+use bytes::BytesMut;
+use futures_util::StreamExt;
+use tauri::Manager;
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum NdjsonMessage {
+    Value {
+        value: serde_json::Value,
+    },
+    /// 2xx以外のレスポンスの場合は`status`を含む。
+    Error {
+        message: String,
+        status: Option<u16>,
+    },
+    Close,
+}
+
+impl NdjsonMessage {
+    fn error(message: String) -> Self {
+        NdjsonMessage::Error {
+            message,
+            status: None,
+        }
+    }
+}
+
+pub async fn ndjson<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), FetchError> {
+    let prepared = prepare(&app, "ndjson", url, options).await?;
+    let event = format!("cookie-fetch://ndjson/{}", channel);
+
+    let tags = prepared.send_options.tags.clone();
+    subscriptions::spawn(&app, channel, {
+        let app = app.clone();
+        async move {
+            let state = app.state::<CookieFetchState>();
+            let task = async {
+                run(&app, &event, prepared).await;
+                Ok::<_, FetchError>(())
+            };
+            if let Err(e) = state.aborts.run(&tags, task).await {
+                let _ = app.emit_all(&event, NdjsonMessage::error(e.to_string()));
+            }
+            let _ = app.emit_all(&event, NdjsonMessage::Close);
+        }
+    });
+
+    Ok(())
+}
+
+async fn run<R: tauri::Runtime>(app: &tauri::AppHandle<R>, event: &str, prepared: Prepared) {
+    let emit = |message: NdjsonMessage| {
+        let _ = app.emit_all(event, message);
+    };
+
+    let Prepared {
+        client,
+        request,
+        response_options,
+        send_options,
+    } = prepared;
+
+    let request = match request.build() {
+        Ok(v) => v,
+        Err(e) => return emit(NdjsonMessage::error(e.to_string())),
+    };
+    let res = match send(app, &client, request, send_options).await {
+        Ok(v) => v,
+        Err(e) => return emit(NdjsonMessage::error(e.to_string())),
+    };
+    // エラーページを行ごとにパースしないよう、ボディを読まずに止める
+    if !res.status().is_success() {
+        return emit(status_error(app, res, response_options.throw_on_error).await);
+    }
+
+    let mut body = response_body(&app.state::<CookieFetchState>(), res);
+    let mut buf = Vec::new();
+    loop {
+        match body.next().await {
+            Some(Ok(chunk)) => {
+                buf.extend_from_slice(&chunk);
+                let mut start = 0;
+                while let Some(i) = buf[start..].iter().position(|b| *b == b'\n') {
+                    emit_line(&emit, &buf[start..start + i]);
+                    start += i + 1;
+                }
+                buf.drain(..start);
+            }
+            None => break,
+            Some(Err(e)) => return emit(NdjsonMessage::error(e.to_string())),
+        }
+    }
+
+    emit_line(&emit, &buf);
+}
+
+/// `throw_on_error`が有効な場合は、`fetch`と同じようにボディの先頭をメッセージに含める。
+async fn status_error<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    res: reqwest::Response,
+    throw_on_error: ThrowOnError,
+) -> NdjsonMessage {
+    let status = res.status().as_u16();
+    let ThrowOnError::Enabled { max_body_length } = throw_on_error else {
+        return NdjsonMessage::Error {
+            message: format!("unexpected response with status {}", status),
+            status: Some(status),
+        };
+    };
+
+    let state = app.state::<CookieFetchState>();
+    let mut headers: HeaderMap = res.headers().clone().into();
+    state.config.redaction.apply(&mut headers);
+    let mut body = BytesMut::new();
+    let mut stream = response_body(&state, res);
+    while let Some(Ok(chunk)) = stream.next().await {
+        body.extend_from_slice(&chunk);
+        if max_body_length.is_some_and(|max| body.len() >= max) {
+            break;
+        }
+    }
+    if let Some(max) = max_body_length {
+        body.truncate(max);
+    }
+
+    let e = FetchError::Status {
+        status,
+        headers,
+        body: body.freeze(),
+    };
+    NdjsonMessage::Error {
+        message: e.to_string(),
+        status: Some(status),
+    }
+}
+
+fn emit_line(emit: &impl Fn(NdjsonMessage), line: &[u8]) {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return;
+    }
+
+    emit(match serde_json::from_slice(line) {
+        Ok(value) => NdjsonMessage::Value { value },
+        Err(e) => NdjsonMessage::error(e.to_string()),
+    });
+}
@@ -0,0 +1,410 @@
+use super::{fetch, fetch::is_allowed, Body, FetchError, FetchOptions, HeaderMap, Payload};
+use crate::{snapshot::SnapshotFile, CookieFetchState};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+use tauri::Manager;
+use tokio::time::Instant;
+
+const OUTBOX_FILE_NAME: &str = "cookie-fetch-outbox.json";
+const OUTBOX_EVENT: &str = "cookie-fetch://outbox";
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// オフラインの間、接続を確かめるために1件だけ送る間隔
+const OFFLINE_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// ディスクに保存するため、ファイルやproviderのボディは使えない。
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxRequest {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default = "HeaderMap::new")]
+    pub headers: HeaderMap,
+    #[serde(default)]
+    pub body: Option<Payload>,
+    /// 同じqueueのリクエストは順に送られ、先頭が送れるまで後ろは待つ。
+    #[serde(default)]
+    pub queue: Option<String>,
+    /// `abort_by_tag`でoutboxから取り除く。送信中のものは中断して`Dropped`になる。
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxItem {
+    pub id: u64,
+    pub request: OutboxRequest,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum OutboxEvent {
+    Sent {
+        id: u64,
+        queue: Option<String>,
+        status: u16,
+    },
+    /// 再送される。
+    Failed {
+        id: u64,
+        queue: Option<String>,
+        attempts: u32,
+        message: String,
+    },
+    /// 再送しても成功しないため、outboxから取り除かれた。
+    Dropped {
+        id: u64,
+        queue: Option<String>,
+        attempts: u32,
+        message: String,
+    },
+}
+
+/// ファイルに保存する内容。idは取り除かれたものも含めて使い回さない。
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stored {
+    next_id: u64,
+    items: VecDeque<OutboxItem>,
+}
+
+/// 送信待ちのリクエスト。変更のたびにファイルへ書き出し、再起動後に読み込む。
+pub struct Outbox {
+    file: SnapshotFile,
+    stored: Mutex<Stored>,
+    changed: tokio::sync::Notify,
+}
+
+impl Outbox {
+    /// 読み込めないファイルは空の outbox として扱う。
+    pub fn load(dir: Option<PathBuf>) -> Self {
+        let path = dir.map(|dir| dir.join(OUTBOX_FILE_NAME));
+        let stored = path
+            .as_deref()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+
+        Self {
+            file: SnapshotFile::new(path),
+            stored: Mutex::new(stored),
+            changed: tokio::sync::Notify::new(),
+        }
+    }
+
+    pub fn enqueue(
+        &self,
+        state: &CookieFetchState,
+        request: OutboxRequest,
+    ) -> Result<u64, FetchError> {
+        let url = match reqwest::Url::parse(&request.url) {
+            Ok(v) => v,
+            Err(_) => return Err(FetchError::InvalidUrl),
+        };
+        if !is_allowed(state, "outbox_enqueue", &url) {
+            return Err(FetchError::NotAllowed);
+        }
+        if reqwest::Method::from_bytes(request.method.as_bytes()).is_err() {
+            return Err(FetchError::InvalidMethod(request.method));
+        }
+
+        let mut stored = self.stored.lock().unwrap();
+        let id = stored.next_id;
+        stored.next_id += 1;
+        stored.items.push_back(OutboxItem {
+            id,
+            request,
+            attempts: 0,
+            last_error: None,
+        });
+        self.save(&stored);
+        drop(stored);
+
+        self.changed.notify_one();
+        Ok(id)
+    }
+
+    pub fn items(&self) -> Vec<OutboxItem> {
+        self.stored.lock().unwrap().items.iter().cloned().collect()
+    }
+
+    pub fn remove(&self, id: u64) -> bool {
+        let mut stored = self.stored.lock().unwrap();
+        let len = stored.items.len();
+        stored.items.retain(|item| item.id != id);
+        let removed = stored.items.len() != len;
+        if removed {
+            self.save(&stored);
+        }
+        removed
+    }
+
+    /// `tag`の付いたリクエストを取り除き、その数を返す。
+    pub fn abort(&self, tag: &str) -> usize {
+        let mut stored = self.stored.lock().unwrap();
+        let len = stored.items.len();
+        stored
+            .items
+            .retain(|item| !item.request.tags.iter().any(|t| t == tag));
+        let removed = len - stored.items.len();
+        if removed > 0 {
+            self.save(&stored);
+        }
+        removed
+    }
+
+    /// queueごとの先頭
+    fn heads(&self) -> Vec<OutboxItem> {
+        let stored = self.stored.lock().unwrap();
+        let mut queues = HashSet::new();
+        stored
+            .items
+            .iter()
+            .filter(|item| queues.insert(item.request.queue.clone()))
+            .cloned()
+            .collect()
+    }
+
+    fn failed(&self, id: u64, message: String) {
+        let mut stored = self.stored.lock().unwrap();
+        if let Some(item) = stored.items.iter_mut().find(|item| item.id == id) {
+            item.attempts += 1;
+            item.last_error = Some(message);
+            self.save(&stored);
+        }
+    }
+
+    /// 書き込みはバックグラウンドで行い、失敗しても送信は続ける。
+    fn save(&self, stored: &Stored) {
+        if let Ok(bytes) = serde_json::to_vec(stored) {
+            self.file.save(bytes);
+        }
+    }
+}
+
+/// outboxをqueueごとに先頭から順に送り続ける。setupで起動し、アプリが終了するまで動く。
+/// queueはそれぞれ独立に送り、あるqueueの送信や待ちが他のqueueを止めることはない。
+/// 5xx、408、429と送信の失敗はバックオフしながら再送し、それ以外の応答で送信済みとする。
+/// scope外やURL、メソッドの誤りなど、再送しても成功しない失敗は`Dropped`を送って取り除く。
+/// オフラインの間は送らずに待ち、戻ったらバックオフを待たずに全てのqueueを送る。
+/// 結果は1件ごとに`cookie-fetch://outbox`に送る。
+pub async fn flush_outbox<R: tauri::Runtime>(app: tauri::AppHandle<R>) {
+    let app = &app;
+    let state = app.state::<CookieFetchState>();
+    let outbox = &state.outbox;
+
+    let mut retry_at: HashMap<Option<String>, Instant> = HashMap::new();
+    let mut probe_at = Instant::now();
+    let mut was_offline = false;
+    let mut sending = FuturesUnordered::new();
+    let mut in_flight: HashSet<Option<String>> = HashSet::new();
+
+    loop {
+        let now = Instant::now();
+        let offline = state.network.is_offline();
+        if was_offline && !offline {
+            retry_at.clear();
+        }
+        was_offline = offline;
+
+        let heads = outbox
+            .heads()
+            .into_iter()
+            .filter(|item| !in_flight.contains(&item.request.queue))
+            .collect::<Vec<_>>();
+        let (ready, next) = if offline {
+            // 接続できるようになったかは、他のリクエストが成功するか試しに送るまで分からない
+            let probing = !in_flight.is_empty();
+            let next = (!heads.is_empty() && !probing).then_some(probe_at);
+            let ready = match heads.into_iter().next() {
+                Some(item) if !probing && probe_at <= now => vec![item],
+                _ => Vec::new(),
+            };
+            (ready, next)
+        } else {
+            let next = heads
+                .iter()
+                .filter_map(|item| retry_at.get(&item.request.queue).copied())
+                .filter(|at| now < *at)
+                .min();
+            let ready = heads
+                .into_iter()
+                .filter(|item| match retry_at.get(&item.request.queue) {
+                    Some(at) => *at <= now,
+                    None => true,
+                })
+                .collect::<Vec<_>>();
+            (ready, next)
+        };
+
+        if offline && !ready.is_empty() {
+            probe_at = now + OFFLINE_PROBE_INTERVAL;
+        }
+        for item in ready {
+            in_flight.insert(item.request.queue.clone());
+            sending.push(async move {
+                let result = send_item(app, item.request.clone()).await;
+                (item, result)
+            });
+        }
+
+        tokio::select! {
+            Some((item, result)) = sending.next(), if !sending.is_empty() => {
+                in_flight.remove(&item.request.queue);
+                finish(app, outbox, &mut retry_at, item, result);
+            }
+            _ = outbox.changed.notified() => {}
+            _ = state.network.online(), if offline => {}
+            _ = tokio::time::sleep_until(next.unwrap_or(now)), if next.is_some() => {}
+        }
+    }
+}
+
+/// 送信の結果をoutboxに反映し、`cookie-fetch://outbox`に送る。
+fn finish<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    outbox: &Outbox,
+    retry_at: &mut HashMap<Option<String>, Instant>,
+    item: OutboxItem,
+    result: Result<u16, SendError>,
+) {
+    let queue = item.request.queue;
+    let event = match result {
+        Ok(status) => {
+            outbox.remove(item.id);
+            retry_at.remove(&queue);
+            OutboxEvent::Sent {
+                id: item.id,
+                queue,
+                status,
+            }
+        }
+        Err(SendError::Permanent(message)) => {
+            outbox.remove(item.id);
+            retry_at.remove(&queue);
+            OutboxEvent::Dropped {
+                id: item.id,
+                queue,
+                attempts: item.attempts + 1,
+                message,
+            }
+        }
+        Err(SendError::Transient(message)) => {
+            outbox.failed(item.id, message.clone());
+            retry_at.insert(queue.clone(), Instant::now() + backoff(item.attempts));
+            OutboxEvent::Failed {
+                id: item.id,
+                queue,
+                attempts: item.attempts + 1,
+                message,
+            }
+        }
+    };
+    let _ = app.emit_all(OUTBOX_EVENT, event);
+}
+
+enum SendError {
+    Transient(String),
+    Permanent(String),
+}
+
+impl From<FetchError> for SendError {
+    fn from(e: FetchError) -> Self {
+        match is_permanent(&e) {
+            true => SendError::Permanent(e.to_string()),
+            false => SendError::Transient(e.to_string()),
+        }
+    }
+}
+
+async fn send_item<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    request: OutboxRequest,
+) -> Result<u16, SendError> {
+    let mut options = FetchOptions {
+        headers: request.headers,
+        tags: request.tags,
+        body: match request.body {
+            Some(Payload::Binary(b)) => Body::Bytes(b.to_vec()),
+            Some(Payload::Text(s)) => Body::Bytes(s.into_bytes()),
+            None => Body::new(),
+        },
+        ..FetchOptions::default()
+    };
+    *options.method = match reqwest::Method::from_bytes(request.method.as_bytes()) {
+        Ok(v) => v,
+        Err(_) => return Err(FetchError::InvalidMethod(request.method).into()),
+    };
+
+    match fetch(app.clone(), "outbox_enqueue", request.url, Some(options)).await {
+        Ok(res) if is_retryable(res.status) => {
+            Err(SendError::Transient(format!("status {}", res.status)))
+        }
+        Ok(res) => Ok(res.status),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 設定やリクエストの誤りで、再送しても同じ結果になるもの
+fn is_permanent(e: &FetchError) -> bool {
+    matches!(
+        e,
+        FetchError::NotAllowed
+            | FetchError::InvalidUrl
+            | FetchError::ProxyAuthRequired
+            | FetchError::InvalidMethod(_)
+            | FetchError::Aborted
+    )
+}
+
+fn is_retryable(status: u16) -> bool {
+    status >= 500 || status == 408 || status == 429
+}
+
+/// `attempts`回失敗した後に待つ時間。1秒から倍にしていき、5分で止める。
+fn backoff(attempts: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempts))
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(9), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn retryable_statuses() {
+        assert!(is_retryable(503));
+        assert!(is_retryable(429));
+        assert!(!is_retryable(404));
+        assert!(!is_retryable(201));
+    }
+
+    #[test]
+    fn permanent_errors() {
+        assert!(is_permanent(&FetchError::NotAllowed));
+        assert!(is_permanent(&FetchError::InvalidMethod("GE T".to_string())));
+        assert!(!is_permanent(&FetchError::NetworkUnreachable));
+    }
+}
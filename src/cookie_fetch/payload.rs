@@ -0,0 +1,127 @@
+use super::{FetchError, PayloadType};
+use base64::Engine;
+use bytes::Bytes;
+
+#[derive(Debug, Clone)]
+pub enum Payload {
+    /// bin-ipcによりMessagePackのbinとしてそのまま送られ、JS側では`Uint8Array`になる。
+    Binary(Bytes),
+    Text(String),
+}
+
+impl Payload {
+    /// `strict`が`true`の場合、不正なUTF-8はエラーになる。`false`の場合はU+FFFDに置き換える。
+    pub fn decode(
+        body: Bytes,
+        payload_type: PayloadType,
+        strict: bool,
+    ) -> Result<Self, FetchError> {
+        match payload_type {
+            PayloadType::Binary => Ok(Payload::Binary(body)),
+            PayloadType::Text if strict => match std::str::from_utf8(&body) {
+                Ok(s) => Ok(Payload::Text(s.to_string())),
+                Err(e) => Err(FetchError::InvalidText {
+                    offset: e.valid_up_to(),
+                }),
+            },
+            PayloadType::Text => Ok(Payload::Text(String::from_utf8_lossy(&body).into_owned())),
+            PayloadType::Base64 => Ok(Payload::Text(
+                base64::engine::general_purpose::STANDARD.encode(&body),
+            )),
+        }
+    }
+}
+
+impl Payload {
+    pub fn len(&self) -> usize {
+        match self {
+            Payload::Binary(b) => b.len(),
+            Payload::Text(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Payload::Binary(b) => &b[..],
+            Payload::Text(s) => s.as_bytes(),
+        }
+    }
+}
+
+impl serde::Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Payload::Binary(b) => serializer.serialize_bytes(b),
+            Payload::Text(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Payload;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("bytes or string")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Payload::Binary(Bytes::copy_from_slice(v)))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Payload::Binary(Bytes::from(v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Payload::Text(v.to_string()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Payload::Text(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut buf = match seq.size_hint() {
+                    Some(len) => Vec::with_capacity(len),
+                    None => Vec::new(),
+                };
+
+                while let Some(b) = seq.next_element::<u8>()? {
+                    buf.push(b);
+                }
+
+                Ok(Payload::Binary(Bytes::from(buf)))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
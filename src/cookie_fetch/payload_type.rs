@@ -0,0 +1,7 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PayloadType {
+    Binary,
+    Text,
+    Base64,
+}
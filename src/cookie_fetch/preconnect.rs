@@ -0,0 +1,17 @@
+use super::{fetch::resolve_url, FetchError};
+use crate::{CookieFetchState, RedirectPolicy};
+
+/// reqwestには接続だけを行う手段がないため、HEADを送ってコネクションをプールに残す。
+/// 直前に返されたclientが次に使われるため、続くリクエストはこのコネクションを再利用できる。
+pub async fn preconnect(state: &CookieFetchState, url: String) -> Result<(), FetchError> {
+    let url = resolve_url(state, "preconnect", &url, None)?;
+
+    let client = state.client_pool.get().await?;
+    *client.redirect_policy() = RedirectPolicy::limited(0);
+    client
+        .execute(reqwest::Request::new(reqwest::Method::HEAD, url))
+        .await
+        .map_err(FetchError::Reqwest)?;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use super::{
+    fetch::{fetch_prepared, prepare},
+    FetchError, FetchOptions,
+};
+use crate::CookieFetchState;
+use std::time::Duration;
+use tauri::Manager;
+
+/// `PrefetchOptions::ttl_ms`を省略した場合に、先読みしたレスポンスを残す時間
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchOptions {
+    /// 設定の`memory_cache.ttlMs`の代わりに使う。省略した場合は1分。
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+}
+
+/// GETを送って設定の`memory_cache`に入れる。ボディは返さない。
+/// キャッシュのキーにはレスポンスの読み方も含まれるため、後で使う`options`と同じものを渡す。
+/// 他のリクエストと帯域を取り合わないよう、一度に1つずつ送る。
+/// 入れたレスポンスは設定の`memory_cache.ttlMs`ではなく`prefetch.ttl_ms`の間残る。
+pub async fn prefetch<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    prefetch: PrefetchOptions,
+) -> Result<(), FetchError> {
+    let state = app.state::<CookieFetchState>();
+    if !state.memory_cache.is_enabled() {
+        return Err(FetchError::CacheDisabled);
+    }
+    if let Some(options) = &options {
+        if *options.method != reqwest::Method::GET {
+            return Err(FetchError::InvalidMethod(options.method.to_string()));
+        }
+    }
+
+    let _prefetching = state.memory_cache.prefetching().await;
+    let mut prepared = prepare(&app, "prefetch", url, options).await?;
+    prepared.response_options.cache_ttl =
+        Some(prefetch.ttl_ms.map_or(DEFAULT_TTL, Duration::from_millis));
+    fetch_prepared(&app, prepared).await.1?;
+
+    Ok(())
+}
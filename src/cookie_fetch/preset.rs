@@ -0,0 +1,41 @@
+use super::{auth, fetch, FetchError, FetchOptions, Response};
+use crate::CookieFetchState;
+use reqwest::header::AUTHORIZATION;
+use tauri::Manager;
+
+/// `name`で登録されたプリセットを`options`の既定値として`path`へfetchする。
+pub async fn fetch_preset<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    name: String,
+    path: String,
+    options: Option<FetchOptions>,
+) -> Result<Response, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    let Some(preset) = state.registry.presets.get(&name).cloned() else {
+        return Err(FetchError::UnknownPreset(name));
+    };
+
+    let mut options = options.unwrap_or_default();
+    for name in preset.headers.keys() {
+        if !options.headers.contains_key(name) {
+            for value in preset.headers.get_all(name) {
+                options.headers.append(name, value.clone());
+            }
+        }
+    }
+    if let Some(credentials) = &preset.credentials {
+        if !options.headers.contains_key(AUTHORIZATION) {
+            options
+                .headers
+                .insert(AUTHORIZATION, auth::header_value(credentials)?);
+        }
+    }
+    if let Some(timeout) = preset.timeout {
+        options
+            .timeouts
+            .total_ms
+            .get_or_insert(timeout.as_millis() as u64);
+    }
+
+    fetch(app, "fetch_preset", preset.url(&path), Some(options)).await
+}
@@ -0,0 +1,77 @@
+use super::{fetch, method::Method, FetchError, FetchOptions, HeaderMap};
+use reqwest::header::{
+    HeaderName, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+    ACCESS_CONTROL_MAX_AGE, ALLOW, SERVER,
+};
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Probe {
+    pub status: u16,
+    /// `Allow`のメソッド
+    pub allow: Vec<String>,
+    pub cors: Cors,
+    pub server: Option<String>,
+    pub headers: HeaderMap,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cors {
+    pub allow_origin: Option<String>,
+    pub allow_methods: Vec<String>,
+    pub allow_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<u64>,
+}
+
+/// OPTIONSを送り、対応するメソッドとCORSのヘッダーを返す。
+/// preflightとして確かめる場合は`options`の`headers`に`Origin`と`Access-Control-Request-Method`を入れる。
+pub async fn probe<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Probe, FetchError> {
+    let mut options = options.unwrap_or_default();
+    options.method = Method::OPTIONS;
+    let response = fetch(app, "probe", url, Some(options)).await?;
+    let headers = response.headers;
+
+    let first = |name: HeaderName| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    };
+    let cors = Cors {
+        allow_origin: first(ACCESS_CONTROL_ALLOW_ORIGIN),
+        allow_methods: list(&headers, ACCESS_CONTROL_ALLOW_METHODS),
+        allow_headers: list(&headers, ACCESS_CONTROL_ALLOW_HEADERS),
+        expose_headers: list(&headers, ACCESS_CONTROL_EXPOSE_HEADERS),
+        allow_credentials: first(ACCESS_CONTROL_ALLOW_CREDENTIALS).as_deref() == Some("true"),
+        max_age: first(ACCESS_CONTROL_MAX_AGE).and_then(|v| v.parse().ok()),
+    };
+
+    Ok(Probe {
+        status: response.status,
+        allow: list(&headers, ALLOW),
+        server: first(SERVER),
+        cors,
+        headers,
+    })
+}
+
+/// カンマ区切りのヘッダーを、同じ名前の全ての値について分ける。
+fn list(headers: &HeaderMap, name: HeaderName) -> Vec<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,303 @@
+use crate::{
+    body_provider::{BodyStream, BoxError},
+    body_transform::ResponseStream,
+};
+use bytes::Bytes;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tauri::Manager;
+
+/// 転送量のイベントを送る最短の間隔
+const INTERVAL: Duration = Duration::from_millis(100);
+/// 速度の指数移動平均の重み
+const SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProgressEvent {
+    /// 429または503に対して`retry_in_ms`待ってから再送する。
+    RateLimited {
+        status: u16,
+        #[serde(rename = "retryInMs")]
+        retry_in_ms: u64,
+    },
+    Upload(Transfer),
+    Download(Transfer),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transfer {
+    pub loaded: u64,
+    /// Content-Lengthなど、わかっている場合のみ
+    pub total: Option<u64>,
+    /// 平滑化した速度
+    pub bytes_per_second: u64,
+    /// `total`と速度がわかっている場合のみ
+    pub eta_ms: Option<u64>,
+}
+
+/// `FetchOptions`の`progress`を指定したリクエストの経過を`cookie-fetch://progress/{channel}`に送る。
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    event: Option<String>,
+}
+
+impl Progress {
+    pub fn new(channel: Option<&str>) -> Self {
+        Self {
+            event: channel.map(|c| format!("cookie-fetch://progress/{}", c)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.event.is_some()
+    }
+
+    pub fn emit<R: tauri::Runtime>(&self, app: &tauri::AppHandle<R>, event: ProgressEvent) {
+        if let Some(name) = &self.event {
+            let _ = app.emit_all(name, event);
+        }
+    }
+
+    pub fn upload<R: tauri::Runtime>(
+        &self,
+        app: &tauri::AppHandle<R>,
+        total: Option<u64>,
+        body: BodyStream,
+    ) -> BodyStream {
+        if !self.is_enabled() {
+            return body;
+        }
+        Box::pin(ProgressStream::new(
+            self,
+            app,
+            total,
+            body,
+            ProgressEvent::Upload,
+        ))
+    }
+
+    /// multipartのpartのように、続けて送る`streams`個のストリームを1つの送信として数える。
+    pub fn upload_parts<R: tauri::Runtime>(
+        &self,
+        app: &tauri::AppHandle<R>,
+        total: Option<u64>,
+        streams: usize,
+    ) -> UploadParts<R> {
+        UploadParts {
+            progress: self.clone(),
+            app: app.clone(),
+            meter: Arc::new(Mutex::new(Meter::new(total, Instant::now()))),
+            streams: Arc::new(AtomicUsize::new(streams)),
+        }
+    }
+
+    pub fn download<R: tauri::Runtime>(
+        &self,
+        app: &tauri::AppHandle<R>,
+        total: Option<u64>,
+        body: ResponseStream,
+    ) -> ResponseStream {
+        if !self.is_enabled() {
+            return body;
+        }
+        Box::pin(ProgressStream::new(
+            self,
+            app,
+            total,
+            body,
+            ProgressEvent::Download,
+        ))
+    }
+}
+
+pub struct UploadParts<R: tauri::Runtime> {
+    progress: Progress,
+    app: tauri::AppHandle<R>,
+    meter: Arc<Mutex<Meter>>,
+    /// 読み終えていないストリームの数。最後のものを読み終えたときに完了を送る。
+    streams: Arc<AtomicUsize>,
+}
+
+impl<R: tauri::Runtime> UploadParts<R> {
+    pub fn part(&self, body: BodyStream) -> BodyStream {
+        if !self.progress.is_enabled() {
+            return body;
+        }
+        Box::pin(ProgressStream {
+            inner: body,
+            app: self.app.clone(),
+            progress: self.progress.clone(),
+            meter: Arc::clone(&self.meter),
+            streams: Arc::clone(&self.streams),
+            event: ProgressEvent::Upload,
+            done: false,
+        })
+    }
+}
+
+/// 読んだバイト数から速度と残り時間を求める。
+#[derive(Debug)]
+pub struct Meter {
+    total: Option<u64>,
+    loaded: u64,
+    last: Instant,
+    last_loaded: u64,
+    rate: Option<f64>,
+}
+
+impl Meter {
+    pub fn new(total: Option<u64>, now: Instant) -> Self {
+        Self {
+            total,
+            loaded: 0,
+            last: now,
+            last_loaded: 0,
+            rate: None,
+        }
+    }
+
+    /// 前回から`INTERVAL`経っておらず、`done`でもない場合は`None`を返す。
+    pub fn advance(&mut self, len: u64, now: Instant, done: bool) -> Option<Transfer> {
+        self.loaded += len;
+        let elapsed = now.saturating_duration_since(self.last);
+        if elapsed < INTERVAL && !done {
+            return None;
+        }
+
+        if !elapsed.is_zero() {
+            let sample = (self.loaded - self.last_loaded) as f64 / elapsed.as_secs_f64();
+            self.rate = Some(match self.rate {
+                Some(rate) => SMOOTHING * sample + (1.0 - SMOOTHING) * rate,
+                None => sample,
+            });
+        }
+        self.last = now;
+        self.last_loaded = self.loaded;
+
+        let rate = self.rate.unwrap_or_default();
+        let eta_ms = match self.total {
+            _ if done => Some(0),
+            Some(total) if rate > 0.0 => {
+                Some((total.saturating_sub(self.loaded) as f64 / rate * 1000.0).round() as u64)
+            }
+            _ => None,
+        };
+        Some(Transfer {
+            loaded: self.loaded,
+            total: self.total,
+            bytes_per_second: rate.round() as u64,
+            eta_ms,
+        })
+    }
+}
+
+struct ProgressStream<S, R: tauri::Runtime> {
+    inner: S,
+    app: tauri::AppHandle<R>,
+    progress: Progress,
+    /// `UploadParts`の場合は他のpartと共有する。
+    meter: Arc<Mutex<Meter>>,
+    streams: Arc<AtomicUsize>,
+    event: fn(Transfer) -> ProgressEvent,
+    done: bool,
+}
+
+// `inner`以外はpinする必要がない
+impl<S: Unpin, R: tauri::Runtime> Unpin for ProgressStream<S, R> {}
+
+impl<S, R: tauri::Runtime> ProgressStream<S, R> {
+    fn new(
+        progress: &Progress,
+        app: &tauri::AppHandle<R>,
+        total: Option<u64>,
+        inner: S,
+        event: fn(Transfer) -> ProgressEvent,
+    ) -> Self {
+        Self {
+            inner,
+            app: app.clone(),
+            progress: progress.clone(),
+            meter: Arc::new(Mutex::new(Meter::new(total, Instant::now()))),
+            streams: Arc::new(AtomicUsize::new(1)),
+            event,
+            done: false,
+        }
+    }
+}
+
+impl<S, R> futures_util::Stream for ProgressStream<S, R>
+where
+    S: futures_util::Stream<Item = Result<Bytes, BoxError>> + Unpin,
+    R: tauri::Runtime,
+{
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_next(cx);
+        let (len, done) = match &poll {
+            Poll::Ready(Some(Ok(chunk))) => (chunk.len() as u64, false),
+            Poll::Ready(None) if !this.done => {
+                this.done = true;
+                if this.streams.fetch_sub(1, Ordering::Relaxed) > 1 {
+                    return poll;
+                }
+                (0, true)
+            }
+            _ => return poll,
+        };
+        let transfer = this
+            .meter
+            .lock()
+            .unwrap()
+            .advance(len, Instant::now(), done);
+        if let Some(transfer) = transfer {
+            this.progress.emit(&this.app, (this.event)(transfer));
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn throttles_and_estimates() {
+        let start = Instant::now();
+        let mut meter = Meter::new(Some(1000), start);
+
+        assert_eq!(
+            meter.advance(10, start + Duration::from_millis(50), false),
+            None
+        );
+        let transfer = meter
+            .advance(90, start + Duration::from_millis(100), false)
+            .unwrap();
+        assert_eq!(transfer.loaded, 100);
+        assert_eq!(transfer.bytes_per_second, 1000);
+        assert_eq!(transfer.eta_ms, Some(900));
+    }
+
+    #[test]
+    fn smooths_rate() {
+        let start = Instant::now();
+        let mut meter = Meter::new(None, start);
+
+        meter.advance(100, start + Duration::from_secs(1), false);
+        let transfer = meter
+            .advance(200, start + Duration::from_secs(2), false)
+            .unwrap();
+        assert_eq!(transfer.bytes_per_second, 130);
+        assert_eq!(transfer.eta_ms, None);
+    }
+}
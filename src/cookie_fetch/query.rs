@@ -0,0 +1,102 @@
+#[derive(Debug)]
+pub struct Query(Vec<(String, String)>);
+
+impl Query {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn append_to(&self, url: &mut reqwest::Url) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        url.query_pairs_mut().extend_pairs(self.0.iter());
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum QueryValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl<'de> serde::Deserialize<'de> for Query {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Query;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("map of string or string[], or sequence of [key, value]")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut pairs = Vec::new();
+
+                while let Some((key, value)) = map.next_entry::<String, QueryValue>()? {
+                    match value {
+                        QueryValue::One(v) => pairs.push((key, v)),
+                        QueryValue::Many(vs) => {
+                            pairs.extend(vs.into_iter().map(|v| (key.clone(), v)))
+                        }
+                    }
+                }
+
+                Ok(Query(pairs))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut pairs = match seq.size_hint() {
+                    Some(len) => Vec::with_capacity(len),
+                    None => Vec::new(),
+                };
+
+                while let Some(pair) = seq.next_element::<(String, String)>()? {
+                    pairs.push(pair);
+                }
+
+                Ok(Query(pairs))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn append_repeated_keys() {
+        let query: Query = serde_json::from_str(r#"{"a":["1","2"],"b":"x y&z"}"#).unwrap();
+        let mut url = reqwest::Url::parse("https://example.com/p?c=0").unwrap();
+        query.append_to(&mut url);
+
+        assert_eq!(url.query(), Some("c=0&a=1&a=2&b=x+y%26z"));
+    }
+
+    #[test]
+    fn deserialize_pairs() {
+        let query: Query = serde_json::from_str(r#"[["a","1"],["a","2"]]"#).unwrap();
+
+        assert_eq!(
+            query.0,
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("a".to_string(), "2".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use super::{fetch, FetchError, FetchOptions, Response};
+use reqwest::header::{HeaderValue, CONTENT_RANGE, RANGE};
+
+/// `Content-Range`で返された範囲。`end`は含む。
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+    /// `*`の場合は`None`
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeResponse {
+    pub response: Response,
+    pub range: ByteRange,
+}
+
+/// `start`から`end`(含む)までを要求し、206と`Content-Range`が要求と合うことを確かめる。
+/// `end`を省略した場合は最後まで。
+pub async fn fetch_range<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    start: u64,
+    end: Option<u64>,
+    options: Option<FetchOptions>,
+) -> Result<RangeResponse, FetchError> {
+    let mut options = options.unwrap_or_default();
+    let range = match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    };
+    // 数字と記号のみからなる
+    options
+        .headers
+        .insert(RANGE, HeaderValue::from_str(&range).unwrap());
+
+    let response = fetch(app, "fetch_range", url, Some(options)).await?;
+    if response.status != 206 {
+        return Err(FetchError::RangeNotSatisfied(response.status));
+    }
+    let range = response
+        .headers
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+        .filter(|r| r.start == start && end.map_or(true, |end| r.end <= end))
+        .ok_or(FetchError::InvalidContentRange)?;
+
+    Ok(RangeResponse { response, range })
+}
+
+fn parse_content_range(value: &str) -> Option<ByteRange> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let range = ByteRange {
+        start: start.trim().parse().ok()?,
+        end: end.trim().parse().ok()?,
+        total: match total.trim() {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        },
+    };
+    (range.start <= range.end).then_some(range)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(
+            parse_content_range("bytes 0-499/1234"),
+            Some(ByteRange {
+                start: 0,
+                end: 499,
+                total: Some(1234)
+            })
+        );
+        assert_eq!(
+            parse_content_range("bytes 500-999/*"),
+            Some(ByteRange {
+                start: 500,
+                end: 999,
+                total: None
+            })
+        );
+    }
+
+    #[test]
+    fn reject_invalid_range() {
+        assert_eq!(parse_content_range("bytes */1234"), None);
+        assert_eq!(parse_content_range("bytes 10-5/20"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{
+    content_disposition::suggested_filename,
+    fetch::{collect_cookies, prepare, Prepared},
+    idn,
+    response_body::response_body,
+    send::send,
+    timeouts, CookieProps, FetchError, FetchOptions, HeaderMap, Payload,
+};
+use crate::{network, CookieFetchState};
+use reqwest::header::CONTENT_DISPOSITION;
+use std::collections::HashMap;
+use tauri::Manager;
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseHead {
+    pub rid: u64,
+    pub url: String,
+    pub unicode_url: Option<String>,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub cookies: HashMap<String, HashMap<String, CookieProps>>,
+    pub suggested_filename: Option<String>,
+    pub remote_address: Option<String>,
+    pub local_address: Option<String>,
+    pub proxy: Option<String>,
+}
+
+/// ボディを読まずにステータスとヘッダを返す。ボディは`read_chunk`で読む。
+pub async fn fetch_begin<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<ResponseHead, FetchError> {
+    let Prepared {
+        client,
+        request,
+        response_options,
+        send_options,
+    } = prepare(&app, "fetch_begin", url, options).await?;
+    let state = app.state::<CookieFetchState>();
+
+    let request = match request.build() {
+        Ok(v) => v,
+        Err(e) => return Err(FetchError::Reqwest(e)),
+    };
+    let mut log = state.logger.begin(&request, &send_options.tags);
+    client.set_request_id(Some(log.id()));
+
+    let tags = send_options.tags.clone();
+    let res = match state
+        .aborts
+        .run(&tags, send(&app, &client, request, send_options))
+        .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            state.logger.write(&log.finish(None, None, Some(&e)));
+            return Err(e);
+        }
+    };
+    state.logger.response(&mut log, res.headers(), None);
+    state
+        .logger
+        .write(&log.finish(Some(res.status().as_u16()), res.content_length(), None));
+
+    let cookies = collect_cookies(&client);
+    let url = res.url().to_string();
+    let unicode_url = idn::unicode_url(res.url());
+    let status = res.status().as_u16();
+    let headers: HeaderMap = res.headers().clone().into();
+    if !response_options.lenient_headers {
+        if let Some(name) = headers.invalid_value() {
+            return Err(FetchError::InvalidHeaderValue(name.to_string()));
+        }
+    }
+    let remote_address = res.remote_addr().map(|a| a.to_string());
+    let local_address = network::local_addr(&res).map(|a| a.to_string());
+    let suggested_filename = res
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(suggested_filename);
+
+    let total = res.content_length();
+    let body = state
+        .bandwidth
+        .download(&response_options.bandwidth, response_body(&state, res));
+    let body = response_options.progress.download(&app, total, body);
+    let rid = state
+        .readers
+        .insert(body, response_options.read_timeout, tags);
+
+    Ok(ResponseHead {
+        rid,
+        url,
+        unicode_url,
+        status,
+        headers,
+        cookies,
+        suggested_filename,
+        remote_address,
+        local_address,
+        proxy: response_options.proxy,
+    })
+}
+
+/// 最大`max`バイトを読む。ボディを読み切った場合は`None`を返し、`rid`は解放される。
+pub async fn read_chunk(
+    state: &CookieFetchState,
+    rid: u64,
+    max: usize,
+) -> Result<Option<Payload>, FetchError> {
+    let Some(reader) = state.readers.get(rid) else {
+        return Err(FetchError::InvalidResourceId(rid));
+    };
+    let mut reader = reader.lock().await;
+
+    while reader.pending.is_empty() {
+        let read_timeout = reader.read_timeout;
+        match timeouts::chunk(&mut reader.body, read_timeout).await {
+            Ok(Some(chunk)) => reader.pending = chunk,
+            Ok(None) => {
+                state.readers.remove(rid);
+                return Ok(None);
+            }
+            Err(e) => {
+                state.readers.remove(rid);
+                return Err(e);
+            }
+        }
+    }
+
+    let len = max.clamp(1, reader.pending.len());
+    Ok(Some(Payload::Binary(reader.pending.split_to(len))))
+}
+
+pub fn close_body(state: &CookieFetchState, rid: u64) {
+    state.readers.remove(rid);
+}
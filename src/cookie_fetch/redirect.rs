@@ -2,7 +2,11 @@
 pub enum Redirect {
     Follow,
     Manual,
-    Limit { limit: usize },
+    Limit {
+        limit: usize,
+    },
+    /// 各リダイレクトをフロントエンドに問い合わせる
+    Interactive,
 }
 
 impl<'de> serde::Deserialize<'de> for Redirect {
@@ -44,7 +48,7 @@ impl<'de> serde::Deserialize<'de> for Redirect {
             type Value = Redirect;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("`follow`, `manual`, or `{ limit: number }`")
+                formatter.write_str("`follow`, `manual`, `interactive`, or `{ limit: number }`")
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -54,6 +58,7 @@ impl<'de> serde::Deserialize<'de> for Redirect {
                 match v {
                     "follow" => Ok(Redirect::Follow),
                     "manual" => Ok(Redirect::Manual),
+                    "interactive" => Ok(Redirect::Interactive),
                     _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
                 }
             }
@@ -0,0 +1,76 @@
+use super::FetchError;
+use crate::{cookie_client::ProxyOverride, proxy::ProxyConfig};
+
+/// このリクエストだけで設定のプロキシの代わりに使うもの
+#[derive(Debug)]
+pub enum RequestProxy {
+    /// 設定や環境変数のプロキシを使わずに直接接続する。
+    Direct,
+    Proxy(ProxyConfig),
+}
+
+/// `url`へのリクエストで使われる、設定またはこのリクエストのプロキシ。環境変数のプロキシはわからない。
+pub fn used_proxy(
+    configured: &[ProxyConfig],
+    request: Option<&RequestProxy>,
+    url: &reqwest::Url,
+) -> Option<String> {
+    let proxy = match request {
+        Some(RequestProxy::Direct) => return None,
+        Some(RequestProxy::Proxy(config)) => Some(config).filter(|c| c.matches(url)),
+        None => configured.iter().find(|c| c.matches(url)),
+    };
+    proxy.and_then(ProxyConfig::display_url)
+}
+
+impl RequestProxy {
+    pub fn to_override(&self) -> Result<ProxyOverride, FetchError> {
+        match self {
+            RequestProxy::Direct => Ok(ProxyOverride::Direct),
+            RequestProxy::Proxy(config) => config
+                .to_reqwest()
+                .map(ProxyOverride::Proxy)
+                .map_err(|_| FetchError::InvalidUrl),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for RequestProxy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct V;
+        impl<'de> serde::de::Visitor<'de> for V {
+            type Value = RequestProxy;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "`direct` or `{ url: string, username?: string, password?: string }`",
+                )
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match v {
+                    "direct" => Ok(RequestProxy::Direct),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+                }
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let config = <ProxyConfig as serde::Deserialize>::deserialize(
+                    serde::de::value::MapAccessDeserializer::new(map),
+                )?;
+                Ok(RequestProxy::Proxy(config))
+            }
+        }
+
+        deserializer.deserialize_any(V)
+    }
+}
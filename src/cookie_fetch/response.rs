@@ -1,13 +1,35 @@
-use super::{CookieProps, HeaderMap};
-use bytes::Bytes;
+use super::{ContentType, CookieProps, HeaderMap, Payload};
 use std::collections::HashMap;
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
     pub url: String,
+    /// ホストが国際化ドメイン名の場合に、`url`のホストをUnicodeに戻したもの
+    #[serde(default)]
+    pub unicode_url: Option<String>,
     pub status: u16,
     pub headers: HeaderMap,
     pub cookies: HashMap<String, HashMap<String, CookieProps>>,
-    pub body: Bytes,
+    pub body: Payload,
+    #[serde(default)]
+    pub suggested_filename: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<ContentType>,
+    /// レスポンスのContent-Length
+    #[serde(default)]
+    pub content_length: Option<u64>,
+    /// 読んだボディのバイト数。切り詰めた場合は捨てた分も含む。
+    #[serde(default)]
+    pub received_bytes: u64,
+    #[serde(default)]
+    pub truncated: bool,
+    /// 接続した相手のアドレス。プロキシを使った場合はプロキシのアドレス。
+    #[serde(default)]
+    pub remote_address: Option<String>,
+    /// 接続に使われたローカルのアドレス
+    #[serde(default)]
+    pub local_address: Option<String>,
+    #[serde(default)]
+    pub proxy: Option<String>,
 }
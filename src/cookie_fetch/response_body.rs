@@ -0,0 +1,26 @@
+use super::FetchError;
+use crate::{body_provider::BoxError, body_transform::ResponseStream, CookieFetchState};
+use futures_util::TryStreamExt;
+
+/// 登録された`ResponseTransform`のうち、URLにマッチするものを適用したボディ
+pub fn response_body(state: &CookieFetchState, res: reqwest::Response) -> ResponseStream {
+    let url = res.url().clone();
+    let headers = res.headers().clone();
+
+    let mut body: ResponseStream =
+        Box::pin(res.bytes_stream().map_err(|e| Box::new(e) as BoxError));
+    for (pattern, transform) in state.registry.response_transforms.iter() {
+        if pattern.matches(url.as_str()) {
+            body = transform.transform(&url, &headers, body);
+        }
+    }
+    body
+}
+
+/// reqwestのエラーはそのまま`FetchError::Reqwest`として扱う。
+pub fn body_error(e: BoxError) -> FetchError {
+    match e.downcast::<reqwest::Error>() {
+        Ok(e) => FetchError::Reqwest(*e),
+        Err(e) => FetchError::BodyTransform(e),
+    }
+}
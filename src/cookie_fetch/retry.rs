@@ -0,0 +1,91 @@
+use crate::CookieClient;
+use reqwest::Method;
+use std::io::ErrorKind;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryConfig {
+    /// 接続エラーの際に一度だけ再送するメソッド。空の場合は再送しない。
+    #[serde(default = "default_methods")]
+    pub methods: Vec<String>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            methods: default_methods(),
+        }
+    }
+}
+
+fn default_methods() -> Vec<String> {
+    vec!["GET".to_string(), "HEAD".to_string()]
+}
+
+impl RetryConfig {
+    pub fn is_retryable(&self, method: &Method) -> bool {
+        self.methods
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(method.as_str()))
+    }
+}
+
+/// プールに残った古い接続がサーバーに閉じられていた場合のエラーに限り再送する。
+pub async fn execute(
+    config: &RetryConfig,
+    client: &CookieClient,
+    request: reqwest::Request,
+) -> reqwest::Result<reqwest::Response> {
+    let retry = if config.is_retryable(request.method()) {
+        request.try_clone()
+    } else {
+        None
+    };
+
+    match client.execute(request).await {
+        Err(e) if is_transient(&e) => match retry {
+            Some(retry) => client.execute(retry).await,
+            None => Err(e),
+        },
+        result => result,
+    }
+}
+
+fn is_transient(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<std::io::Error>() {
+            return matches!(
+                e.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe
+            );
+        }
+        // 応答の前に閉じられた接続はio::Errorではなくhyperのエラーになる
+        if e.to_string()
+            .contains("connection closed before message completed")
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retryable_methods() {
+        let config = RetryConfig::default();
+        assert!(config.is_retryable(&Method::GET));
+        assert!(config.is_retryable(&Method::HEAD));
+        assert!(!config.is_retryable(&Method::POST));
+
+        let config = RetryConfig {
+            methods: vec!["put".to_string()],
+        };
+        assert!(config.is_retryable(&Method::PUT));
+        assert!(!config.is_retryable(&Method::GET));
+    }
+}
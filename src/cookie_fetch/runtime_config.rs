@@ -0,0 +1,200 @@
+use super::{timeouts::Timeouts, FetchError, FetchOptions, HeaderMap};
+use crate::{cookie_client::CookieClientPool, proxy::ProxyConfig, redaction::Redaction};
+use std::sync::{Arc, RwLock};
+
+/// `set_config`で変える設定。省略した項目は変えない。
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigPatch {
+    /// 設定の`proxies`を置き換える。
+    #[serde(default)]
+    pub proxies: Option<Vec<ProxyConfig>>,
+    #[serde(default)]
+    pub timeouts: Option<Timeouts>,
+    #[serde(default)]
+    pub default_headers: Option<HeaderMap>,
+    /// `null`で上限をなくす。
+    #[serde(default, with = "serde_with::rust::double_option")]
+    pub max_body_bytes: Option<Option<u64>>,
+}
+
+/// 実行中に変えられる設定。リクエストで指定されなかった場合に使う。
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeSettings {
+    pub proxies: Vec<ProxyConfig>,
+    pub timeouts: Timeouts,
+    pub default_headers: HeaderMap,
+    pub max_body_bytes: Option<u64>,
+}
+
+impl RuntimeSettings {
+    fn is_empty(&self) -> bool {
+        self.default_headers.is_empty()
+            && self.max_body_bytes.is_none()
+            && self.timeouts.read_ms.is_none()
+            && self.timeouts.total_ms.is_none()
+    }
+
+    /// プロキシと接続のタイムアウトはプールのclientに設定されるため、ここでは扱わない。
+    pub fn apply(&self, options: Option<FetchOptions>) -> Option<FetchOptions> {
+        if self.is_empty() {
+            return options;
+        }
+
+        let mut options = options.unwrap_or_default();
+        for name in self.default_headers.keys() {
+            if !options.headers.contains_key(name) {
+                for value in self.default_headers.get_all(name) {
+                    options.headers.append(name, value.clone());
+                }
+            }
+        }
+        let timeouts = &mut options.timeouts;
+        timeouts.read_ms = timeouts.read_ms.or(self.timeouts.read_ms);
+        timeouts.total_ms = timeouts.total_ms.or(self.timeouts.total_ms);
+        options.max_body_bytes = options.max_body_bytes.or(self.max_body_bytes);
+
+        Some(options)
+    }
+}
+
+/// `get_config`が返す現在の値。プロキシの認証情報と伏せるヘッダーの値は含まない。
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    pub proxies: Vec<EffectiveProxy>,
+    pub timeouts: Timeouts,
+    pub default_headers: HeaderMap,
+    pub max_body_bytes: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveProxy {
+    pub url: Option<String>,
+    pub targets: Vec<String>,
+}
+
+/// 変更は次に始まるリクエストから使われ、送信中のリクエストには影響しない。
+pub struct RuntimeConfig {
+    current: RwLock<Arc<RuntimeSettings>>,
+}
+
+impl RuntimeConfig {
+    pub fn new(settings: RuntimeSettings) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(settings)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<RuntimeSettings> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    /// プロキシか接続のタイムアウトが変わった場合は、プールのclientを作り直させる。
+    /// 同時に呼ばれても設定とプールが食い違わないよう、プールを更新し終えるまでロックを持つ。
+    pub fn update(
+        &self,
+        patch: ConfigPatch,
+        client_pool: &CookieClientPool,
+    ) -> Result<(), FetchError> {
+        let proxies = match &patch.proxies {
+            Some(proxies) => Some(
+                proxies
+                    .iter()
+                    .map(ProxyConfig::to_reqwest)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(FetchError::InvalidProxy)?,
+            ),
+            None => None,
+        };
+
+        let mut current = self.current.write().unwrap();
+        let mut settings = RuntimeSettings::clone(&current);
+        let connect_changed = patch
+            .timeouts
+            .as_ref()
+            .is_some_and(|t| t.connect_ms != settings.timeouts.connect_ms);
+        if let Some(v) = patch.proxies {
+            settings.proxies = v;
+        }
+        if let Some(v) = patch.timeouts {
+            settings.timeouts = v;
+        }
+        if let Some(v) = patch.default_headers {
+            settings.default_headers = v;
+        }
+        if let Some(v) = patch.max_body_bytes {
+            settings.max_body_bytes = v;
+        }
+
+        if proxies.is_some() || connect_changed {
+            let proxies = match proxies {
+                Some(v) => v,
+                None => settings
+                    .proxies
+                    .iter()
+                    .map(ProxyConfig::to_reqwest)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(FetchError::InvalidProxy)?,
+            };
+            client_pool.set_connection(proxies, settings.timeouts.connect());
+        }
+        *current = Arc::new(settings);
+
+        Ok(())
+    }
+
+    pub fn effective(&self, redaction: &Redaction) -> EffectiveConfig {
+        let settings = self.current();
+        let mut default_headers = settings.default_headers.clone();
+        redaction.apply(&mut default_headers);
+
+        EffectiveConfig {
+            proxies: settings
+                .proxies
+                .iter()
+                .map(|p| EffectiveProxy {
+                    url: p.display_url(),
+                    targets: p.targets.iter().map(|t| t.as_str().to_string()).collect(),
+                })
+                .collect(),
+            timeouts: settings.timeouts.clone(),
+            default_headers,
+            max_body_bytes: settings.max_body_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn request_options_take_precedence() {
+        let mut settings = RuntimeSettings::default();
+        settings
+            .default_headers
+            .insert("x-client", HeaderValue::from_static("app"));
+        settings
+            .default_headers
+            .insert("accept", HeaderValue::from_static("*/*"));
+        settings.timeouts.total_ms = Some(5000);
+        settings.max_body_bytes = Some(1024);
+
+        let mut options = FetchOptions::default();
+        options
+            .headers
+            .insert("accept", HeaderValue::from_static("text/html"));
+        options.timeouts.total_ms = Some(100);
+
+        let options = settings.apply(Some(options)).unwrap();
+        assert_eq!(options.headers["x-client"], "app");
+        assert_eq!(options.headers["accept"], "text/html");
+        assert_eq!(options.timeouts.total_ms, Some(100));
+        assert_eq!(options.max_body_bytes, Some(1024));
+
+        assert!(RuntimeSettings::default().apply(None).is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use super::{
+    fetch::{fetch_prepared, prepare_detached, Prepared},
+    BatchResult, FetchError, FetchOptions,
+};
+use crate::subscriptions;
+use std::time::Duration;
+use tauri::Manager;
+
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleOptions {
+    pub interval_ms: u64,
+    /// 省略した場合は`interval_ms`後に最初のリクエストを送る。
+    #[serde(default)]
+    pub immediate: bool,
+}
+
+/// `interval_ms`(最短1秒)ごとに`url`をfetchし、結果を`cookie-fetch://schedule/{channel}`に送る。
+/// Rust側で動くため、webviewが応答しない間やウィンドウを閉じた後も続き、`unsubscribe`で止める。
+/// 同じjarを使い続けるため、プールの外のclientを使う。間隔の間もプールのclientを塞がない。
+pub async fn schedule<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    schedule: ScheduleOptions,
+    channel: String,
+) -> Result<(), FetchError> {
+    let prepared = prepare_detached(&app, "schedule", url, options).await?;
+    // ボディを送るたびに作り直せるか、ここで確かめておく
+    if prepared.request.try_clone().is_none() {
+        return Err(FetchError::UnsupportedBody("streamed"));
+    }
+    let event = format!("cookie-fetch://schedule/{}", channel);
+
+    subscriptions::spawn(&app, channel, {
+        let app = app.clone();
+        async move { run(&app, &event, prepared, schedule).await }
+    });
+
+    Ok(())
+}
+
+async fn run<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event: &str,
+    prepared: Prepared,
+    schedule: ScheduleOptions,
+) {
+    let Prepared {
+        mut client,
+        request,
+        response_options,
+        send_options,
+    } = prepared;
+    let mut interval =
+        tokio::time::interval(Duration::from_millis(schedule.interval_ms).max(MIN_INTERVAL));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    if !schedule.immediate {
+        // 最初のtickはすぐに終わる
+        interval.tick().await;
+    }
+
+    loop {
+        interval.tick().await;
+
+        let Some(request) = request.try_clone() else {
+            return;
+        };
+        let prepared = Prepared {
+            client,
+            request,
+            response_options: response_options.clone(),
+            send_options: send_options.clone(),
+        };
+        let (returned, result) = fetch_prepared(app, prepared).await;
+        client = returned;
+
+        let message = match result {
+            Ok(res) => BatchResult::Ok(res),
+            Err(e) => BatchResult::Error {
+                kind: e.kind(),
+                message: e.to_string(),
+            },
+        };
+        let _ = app.emit_all(event, message);
+    }
+}
@@ -0,0 +1,241 @@
+use super::{
+    auth, interactive_redirect,
+    progress::{Progress, ProgressEvent},
+    FetchError,
+};
+use crate::{
+    auth_provider::AuthChallenge, bridge, cookie_client::RedirectBlocked, fault, network,
+    CookieClient, CookieFetchState,
+};
+use reqwest::{
+    header::{
+        AUTHORIZATION, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, RETRY_AFTER, WWW_AUTHENTICATE,
+    },
+    StatusCode,
+};
+use std::time::Duration;
+use tauri::Manager;
+
+const NETWORK_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `prepare`で決まる送信の仕方
+#[derive(Debug, Default, Clone)]
+pub struct SendOptions {
+    /// scopeと同意の確認に使うコマンド名
+    pub command: String,
+    pub interactive_redirect: bool,
+    pub auth_challenge: bool,
+    /// 経路がない場合に、この時間まで接続できるようになるのを待つ。
+    pub wait_for_network: Option<Duration>,
+    /// 429と503のRetry-Afterに従って待つ合計の最大
+    pub retry_after_max: Option<Duration>,
+    pub progress: Progress,
+    pub tags: Vec<String>,
+}
+
+/// 登録されたsignerでの署名は送る試みごとに行う。`bridge.headerToCookie`の規則はレスポンスごとに適用する。
+/// 経路がない場合は`wait_for_network`の間、間隔を空けて再送する。
+/// 429などのRetry-Afterは`retry_after_max`の範囲で待って再送し、待つ間`ProgressEvent::RateLimited`を送る。
+pub async fn send<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    client: &CookieClient,
+    mut request: reqwest::Request,
+    options: SendOptions,
+) -> Result<reqwest::Response, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    let deadline = options
+        .wait_for_network
+        .map(|d| tokio::time::Instant::now() + d);
+
+    let mut retry_after_budget = options.retry_after_max.unwrap_or_default();
+
+    loop {
+        let retry = if deadline.is_some() || options.retry_after_max.is_some() {
+            request.try_clone()
+        } else {
+            None
+        };
+        let in_flight = state.pool_stats.begin(request.url(), &options.tags);
+        let result = send_once(app, client, request, &options).await;
+        in_flight.finish(&result);
+        match &result {
+            Ok(res) => {
+                state.network.report(app, false);
+                bridge::apply_header_to_cookie(&state.config.bridge.header_to_cookie, client, res);
+            }
+            Err(FetchError::NetworkUnreachable) => state.network.report(app, true),
+            Err(_) => {}
+        }
+
+        let Some(retry) = retry else {
+            return result;
+        };
+        let now = tokio::time::Instant::now();
+        match (result, deadline) {
+            (Err(FetchError::NetworkUnreachable), Some(deadline)) if now < deadline => {
+                tokio::time::sleep(NETWORK_RETRY_INTERVAL.min(deadline - now)).await;
+            }
+            (Ok(res), _) => match retry_after(&res).filter(|d| *d <= retry_after_budget) {
+                Some(delay) => {
+                    retry_after_budget -= delay;
+                    options.progress.emit(
+                        app,
+                        ProgressEvent::RateLimited {
+                            status: res.status().as_u16(),
+                            retry_in_ms: delay.as_millis() as u64,
+                        },
+                    );
+                    drop(res);
+                    tokio::time::sleep(delay).await;
+                }
+                None => return Ok(res),
+            },
+            (result, _) => return result,
+        }
+        request = retry;
+    }
+}
+
+/// 日付の形式のRetry-Afterは扱わない。
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    match res.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {}
+        _ => return None,
+    }
+    parse_retry_after(res.headers().get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// `Builder::fault`の障害は送信の試みごとに起こす。
+/// プロキシの認証に失敗した場合は`FetchError::ProxyAuthRequired`になる。
+async fn send_once<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    client: &CookieClient,
+    request: reqwest::Request,
+    options: &SendOptions,
+) -> Result<reqwest::Response, FetchError> {
+    let state = app.state::<CookieFetchState>();
+    if let Some(result) = fault::inject(&state.registry.faults, &request).await {
+        return result;
+    }
+
+    match send_with_auth(app, client, request, options).await {
+        Ok(res) if res.status() == StatusCode::PROXY_AUTHENTICATION_REQUIRED => {
+            Err(FetchError::ProxyAuthRequired)
+        }
+        Err(FetchError::Reqwest(e)) if is_proxy_auth_required(&e) => {
+            Err(FetchError::ProxyAuthRequired)
+        }
+        Err(FetchError::Reqwest(e)) if network::is_unreachable(&e) => {
+            Err(FetchError::NetworkUnreachable)
+        }
+        Err(FetchError::Reqwest(e)) => match redirect_blocked(&e) {
+            Some(url) => Err(FetchError::RedirectBlocked(url)),
+            None => Err(FetchError::Reqwest(e)),
+        },
+        result => result,
+    }
+}
+
+fn redirect_blocked(e: &reqwest::Error) -> Option<String> {
+    if !e.is_redirect() {
+        return None;
+    }
+    std::error::Error::source(e)
+        .and_then(|e| e.downcast_ref::<RedirectBlocked>())
+        .map(|e| e.0.to_string())
+}
+
+/// httpsのCONNECTで407が返った場合、reqwestはレスポンスではなくエラーを返す。
+fn is_proxy_auth_required(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(e) = source {
+        if e.to_string().contains("proxy authentication required") {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// `auth_challenge`が有効な場合、401/407に対して認証情報を得られれば一度だけ再送する。
+async fn send_with_auth<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    client: &CookieClient,
+    request: reqwest::Request,
+    options: &SendOptions,
+) -> Result<reqwest::Response, FetchError> {
+    let retry = if options.auth_challenge {
+        request.try_clone()
+    } else {
+        None
+    };
+
+    let res = interactive_redirect::send(
+        app,
+        &options.command,
+        client,
+        request,
+        options.interactive_redirect,
+    )
+    .await?;
+
+    let Some(mut retry) = retry else {
+        return Ok(res);
+    };
+    let (challenge_header, auth_header) = match res.status() {
+        StatusCode::UNAUTHORIZED => (WWW_AUTHENTICATE, AUTHORIZATION),
+        StatusCode::PROXY_AUTHENTICATION_REQUIRED => (PROXY_AUTHENTICATE, PROXY_AUTHORIZATION),
+        _ => return Ok(res),
+    };
+    // リダイレクト先で求められた場合は再送しない
+    if res.url() != retry.url() {
+        return Ok(res);
+    }
+
+    let challenge = AuthChallenge {
+        status: res.status().as_u16(),
+        url: res.url().to_string(),
+        challenges: res
+            .headers()
+            .get_all(challenge_header)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .map(String::from)
+            .collect(),
+    };
+    let Some(credentials) = auth::credentials(app, &challenge).await else {
+        return Ok(res);
+    };
+
+    retry
+        .headers_mut()
+        .insert(auth_header, auth::header_value(&credentials)?);
+    interactive_redirect::send(
+        app,
+        &options.command,
+        client,
+        retry,
+        options.interactive_redirect,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(parse_retry_after("12"), Some(Duration::from_secs(12)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_date_is_ignored() {
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
@@ -0,0 +1,47 @@
+use super::FetchError;
+use crate::{request_signer::SigningRequest, CookieFetchState};
+use sha2::{Digest, Sha256};
+
+/// ホストがマッチするsignerを登録順に適用する。署名が再送に使われないよう、送る直前に毎回呼ぶ。
+pub async fn sign(
+    state: &CookieFetchState,
+    request: &mut reqwest::Request,
+) -> Result<(), FetchError> {
+    let host = request.url().host_str().unwrap_or_default().to_string();
+    let body_sha256 = request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| Sha256::digest(b).into());
+
+    for (pattern, signer) in &state.registry.request_signers {
+        if !pattern.matches(&host) {
+            continue;
+        }
+        let signing = SigningRequest {
+            method: request.method(),
+            url: request.url(),
+            headers: request.headers(),
+            body_sha256,
+        };
+        let headers = signer
+            .sign(&signing)
+            .await
+            .map_err(FetchError::RequestSigner)?;
+        // 同じ名前の2つ目以降の値は名前が`None`になる
+        let mut last = None;
+        for (name, value) in headers {
+            match name {
+                Some(name) => {
+                    request.headers_mut().insert(&name, value);
+                    last = Some(name);
+                }
+                None => {
+                    if let Some(name) = &last {
+                        request.headers_mut().append(name, value);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
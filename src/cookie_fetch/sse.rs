@@ -0,0 +1,146 @@
+use super::{
+    event_stream::{EventStreamParser, SseEvent},
+    fetch::{prepare_detached, Prepared},
+    response_body::response_body,
+    send::send,
+    FetchError, FetchOptions,
+};
+use crate::{subscriptions, CookieFetchState};
+use futures_util::StreamExt;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use std::time::Duration;
+use tauri::Manager;
+
+const DEFAULT_RETRY: u64 = 3000;
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SseMessage {
+    Open,
+    Event(SseEvent),
+    Error { message: String },
+    Close,
+}
+
+pub async fn sse<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), FetchError> {
+    let prepared = prepare_detached(&app, "sse", url, options).await?;
+    let event = format!("cookie-fetch://sse/{}", channel);
+
+    let tags = prepared.send_options.tags.clone();
+    subscriptions::spawn(&app, channel, {
+        let app = app.clone();
+        async move {
+            let state = app.state::<CookieFetchState>();
+            let task = async {
+                run(&app, &event, prepared).await;
+                Ok::<_, FetchError>(())
+            };
+            if let Err(e) = state.aborts.run(&tags, task).await {
+                let _ = app.emit_all(
+                    &event,
+                    SseMessage::Error {
+                        message: e.to_string(),
+                    },
+                );
+            }
+            let _ = app.emit_all(&event, SseMessage::Close);
+        }
+    });
+
+    Ok(())
+}
+
+async fn run<R: tauri::Runtime>(app: &tauri::AppHandle<R>, event: &str, prepared: Prepared) {
+    let emit_error = |message: String| {
+        let _ = app.emit_all(event, SseMessage::Error { message });
+    };
+
+    // 購読中はクッキーを保持するため、プールの外のクライアントを使い続ける
+    let Prepared {
+        client,
+        request,
+        send_options,
+        ..
+    } = prepared;
+    let request = request.header(ACCEPT, HeaderValue::from_static("text/event-stream"));
+
+    let mut parser = EventStreamParser::new();
+    let mut retry = DEFAULT_RETRY;
+
+    loop {
+        let Some(mut attempt) = request.try_clone() else {
+            emit_error(String::from("request body cannot be replayed"));
+            return;
+        };
+
+        if let Ok(id) = HeaderValue::from_str(parser.last_event_id()) {
+            if !id.is_empty() {
+                attempt = attempt.header("Last-Event-ID", id);
+            }
+        }
+
+        let attempt = match attempt.build() {
+            Ok(v) => v,
+            Err(e) => {
+                emit_error(e.to_string());
+                return;
+            }
+        };
+
+        match send(app, &client, attempt, send_options.clone()).await {
+            Ok(res) => {
+                let is_event_stream = res
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.starts_with("text/event-stream"))
+                    .unwrap_or(false);
+
+                // 200以外、またはtext/event-streamでない場合は再接続しない
+                if res.status() != reqwest::StatusCode::OK || !is_event_stream {
+                    emit_error(format!(
+                        "unexpected response with status {}",
+                        res.status().as_u16()
+                    ));
+                    return;
+                }
+
+                let _ = app.emit_all(event, SseMessage::Open);
+
+                let mut body = response_body(&app.state::<CookieFetchState>(), res);
+                loop {
+                    match body.next().await {
+                        Some(Ok(chunk)) => {
+                            for e in parser.feed(&chunk) {
+                                let _ = app.emit_all(event, SseMessage::Event(e));
+                            }
+                        }
+                        None => break,
+                        Some(Err(e)) => {
+                            emit_error(e.to_string());
+                            break;
+                        }
+                    }
+                }
+            }
+            // scopeや認証による拒否は再接続しても同じ結果になる
+            Err(e) if !e.is_transport() => {
+                emit_error(e.to_string());
+                return;
+            }
+            Err(e) => emit_error(e.to_string()),
+        }
+
+        if let Some(v) = parser.retry() {
+            retry = v;
+        }
+        parser.reset();
+
+        tokio::time::sleep(Duration::from_millis(retry)).await;
+    }
+}
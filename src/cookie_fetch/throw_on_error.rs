@@ -0,0 +1,63 @@
+#[derive(Debug, Clone, Copy)]
+pub enum ThrowOnError {
+    Disabled,
+    Enabled { max_body_length: Option<usize> },
+}
+
+impl ThrowOnError {
+    pub fn new() -> Self {
+        ThrowOnError::Disabled
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ThrowOnError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct V;
+        impl<'de> serde::de::Visitor<'de> for V {
+            type Value = ThrowOnError;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("boolean or `{ maxBodyLength?: number }`")
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(if v {
+                    ThrowOnError::Enabled {
+                        max_body_length: None,
+                    }
+                } else {
+                    ThrowOnError::Disabled
+                })
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut max_body_length = None;
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "maxBodyLength" => max_body_length = map.next_value()?,
+                        _ => {
+                            return Err(<A::Error as serde::de::Error>::unknown_field(
+                                &key,
+                                &["maxBodyLength"],
+                            ))
+                        }
+                    }
+                }
+
+                Ok(ThrowOnError::Enabled { max_body_length })
+            }
+        }
+
+        deserializer.deserialize_any(V)
+    }
+}
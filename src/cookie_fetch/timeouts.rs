@@ -0,0 +1,49 @@
+use super::{response_body::body_error, FetchError};
+use crate::body_transform::ResponseStream;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use std::time::Duration;
+
+/// reqwestはwriteのタイムアウトを扱えないため、書き込みの停滞は`total_ms`でのみ検出できる。
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeouts {
+    /// DNSの解決からTLSのハンドシェイクまで
+    #[serde(default)]
+    pub connect_ms: Option<u64>,
+    /// レスポンスのボディのchunkの間隔
+    #[serde(default)]
+    pub read_ms: Option<u64>,
+    /// 送信を始めてからボディを読み終えるまで
+    #[serde(default)]
+    pub total_ms: Option<u64>,
+}
+
+impl Timeouts {
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect_ms.map(Duration::from_millis)
+    }
+
+    pub fn read(&self) -> Option<Duration> {
+        self.read_ms.map(Duration::from_millis)
+    }
+
+    pub fn total(&self) -> Option<Duration> {
+        self.total_ms.map(Duration::from_millis)
+    }
+}
+
+/// `read`の間に次のchunkが届かない場合は`FetchError::ReadTimeout`になる。
+pub async fn chunk(
+    body: &mut ResponseStream,
+    read: Option<Duration>,
+) -> Result<Option<Bytes>, FetchError> {
+    let next = match read {
+        Some(read) => match tokio::time::timeout(read, body.next()).await {
+            Ok(v) => v,
+            Err(_) => return Err(FetchError::ReadTimeout),
+        },
+        None => body.next().await,
+    };
+    next.transpose().map_err(body_error)
+}
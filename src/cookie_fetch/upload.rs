@@ -0,0 +1,39 @@
+use super::{FetchError, Payload};
+use crate::CookieFetchState;
+use bytes::Bytes;
+
+pub async fn upload_chunk(
+    state: &CookieFetchState,
+    id: u64,
+    data: Payload,
+) -> Result<(), FetchError> {
+    let Some(sender) = state.uploads.sender(id) else {
+        return Err(FetchError::InvalidResourceId(id));
+    };
+
+    let data = match data {
+        Payload::Binary(b) => b,
+        Payload::Text(s) => Bytes::from(s),
+    };
+
+    // リクエストが終了している場合は受信側が破棄されている
+    sender
+        .send(Ok(data))
+        .await
+        .map_err(|_| FetchError::InvalidResourceId(id))
+}
+
+/// `cancel`が`true`の場合、ボディをエラーで終わらせてリクエストを失敗させる。
+pub async fn upload_end(state: &CookieFetchState, id: u64, cancel: bool) {
+    let sender = state.uploads.sender(id);
+    if !cancel {
+        state.uploads.end(id);
+        return;
+    }
+
+    // 未使用の受信側を先に破棄し、送信が待ち続けないようにする
+    state.uploads.cancel(id);
+    if let Some(sender) = sender {
+        let _ = sender.send(Err("upload cancelled".into())).await;
+    }
+}
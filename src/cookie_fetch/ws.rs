@@ -0,0 +1,185 @@
+use super::{
+    consent,
+    fetch::{insert_cookies, resolve_url},
+    FetchError, FetchOptions, Payload,
+};
+use crate::{sockets::Sockets, subscriptions, CookieFetchState};
+use futures_util::{SinkExt, StreamExt};
+use std::borrow::Cow;
+use tauri::{Manager, State};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest,
+    http::{HeaderName, HeaderValue},
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum WsMessage {
+    Open,
+    Text {
+        data: String,
+    },
+    Binary {
+        data: Vec<u8>,
+    },
+    Error {
+        message: String,
+    },
+    Close {
+        code: Option<u16>,
+        reason: Option<String>,
+    },
+}
+
+pub async fn ws_connect<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), FetchError> {
+    let state: State<'_, CookieFetchState> = app.state();
+    consent::ask(&app, "ws_connect", &url).await;
+    let url = resolve_url(&state, "ws_connect", &url, options.as_ref())?;
+    let client = state.client_pool.get().await?;
+    client.set_partition_key(options.as_ref().and_then(|o| o.partition_key.clone()));
+
+    let mut request = match url.as_str().into_client_request() {
+        Ok(v) => v,
+        Err(e) => return Err(FetchError::WebSocket(Box::new(e))),
+    };
+
+    if let Some(options) = options {
+        insert_cookies(&client, options.cookies, &url)?;
+
+        for (name, value) in options.headers.iter() {
+            let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_str().as_bytes()),
+                HeaderValue::from_bytes(value.as_bytes()),
+            ) else {
+                continue;
+            };
+            request.headers_mut().append(name, value);
+        }
+    }
+
+    // クッキーのマッチングはhttp(s)のURLで行う
+    let mut cookie_url = url.clone();
+    let _ = match url.scheme() {
+        "wss" => cookie_url.set_scheme("https"),
+        _ => cookie_url.set_scheme("http"),
+    };
+    if let Some(cookie) = client.cookie_header(&cookie_url) {
+        if let Ok(cookie) = HeaderValue::from_bytes(cookie.as_bytes()) {
+            request.headers_mut().append("cookie", cookie);
+        }
+    }
+    // jarはハンドシェイクのリクエストを作るためだけに使う
+    drop(client);
+
+    let (stream, _) = match tokio_tungstenite::connect_async(request).await {
+        Ok(v) => v,
+        Err(e) => return Err(FetchError::WebSocket(Box::new(e))),
+    };
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    state.sockets.insert(channel.clone(), sender.clone());
+
+    let event = format!("cookie-fetch://ws/{}", channel);
+    subscriptions::spawn(&app, channel.clone(), {
+        let app = app.clone();
+        async move {
+            let state = app.state::<CookieFetchState>();
+            // `unsubscribe`でabortされた場合も外す
+            let _registered = Registered {
+                sockets: &state.sockets,
+                channel: &channel,
+                sender,
+            };
+            let emit = |message: WsMessage| {
+                let _ = app.emit_all(&event, message);
+            };
+            let (mut sink, mut stream) = stream.split();
+
+            emit(WsMessage::Open);
+
+            let close = loop {
+                tokio::select! {
+                    incoming = stream.next() => match incoming {
+                        Some(Ok(Message::Text(data))) => emit(WsMessage::Text { data }),
+                        Some(Ok(Message::Binary(data))) => emit(WsMessage::Binary { data }),
+                        Some(Ok(Message::Close(frame))) => break frame,
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => {
+                            emit(WsMessage::Error { message: e.to_string() });
+                            break None;
+                        }
+                        None => break None,
+                    },
+                    outgoing = receiver.recv() => match outgoing {
+                        Some(message) => {
+                            if let Err(e) = sink.send(message).await {
+                                emit(WsMessage::Error { message: e.to_string() });
+                                break None;
+                            }
+                        }
+                        None => break None,
+                    },
+                }
+            };
+
+            emit(WsMessage::Close {
+                code: close.as_ref().map(|f| u16::from(f.code)),
+                reason: close.map(|f| f.reason.into_owned()),
+            });
+        }
+    });
+
+    Ok(())
+}
+
+struct Registered<'a> {
+    sockets: &'a Sockets,
+    channel: &'a str,
+    sender: UnboundedSender<Message>,
+}
+
+impl Drop for Registered<'_> {
+    fn drop(&mut self) {
+        self.sockets.remove(self.channel, &self.sender);
+    }
+}
+
+pub fn ws_send(state: &CookieFetchState, channel: &str, data: Payload) -> Result<(), FetchError> {
+    let message = match data {
+        Payload::Text(v) => Message::Text(v),
+        Payload::Binary(v) => Message::Binary(v.to_vec()),
+    };
+
+    if !state.sockets.send(channel, message) {
+        return Err(FetchError::SocketClosed);
+    }
+
+    Ok(())
+}
+
+pub fn ws_close(
+    state: &CookieFetchState,
+    channel: &str,
+    code: Option<u16>,
+    reason: Option<String>,
+) -> Result<(), FetchError> {
+    let frame = code.map(|code| CloseFrame {
+        code: CloseCode::from(code),
+        reason: Cow::Owned(reason.unwrap_or_default()),
+    });
+
+    if !state.sockets.send(channel, Message::Close(frame)) {
+        return Err(FetchError::SocketClosed);
+    }
+
+    Ok(())
+}
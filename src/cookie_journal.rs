@@ -0,0 +1,218 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieJournalConfig {
+    /// 0の場合は記録しない。
+    #[serde(default)]
+    pub max_entries: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookieChange {
+    Set,
+    /// 同じdomain、path、nameのcookieを置き換えた。
+    Overwrite,
+    /// `Max-Age`が0以下
+    Delete,
+    /// `Expires`が過去
+    Expire,
+}
+
+/// cookieの値は記録しない。
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// unix time in milliseconds
+    pub timestamp: u64,
+    pub change: CookieChange,
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    /// Set-Cookieを返したレスポンスのURL
+    pub url: String,
+    /// Set-Cookieを返したリクエストの`LogEntry::id`。ログを取らない購読などのリクエストでは`None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+}
+
+/// 保存する前に読んだ、Set-Cookieが変えるcookieの状態
+pub struct PendingChange {
+    change: CookieChange,
+    domain: String,
+    path: String,
+    name: String,
+    value: String,
+    existed: bool,
+    url: String,
+}
+
+/// レスポンスによるjarの変更を、新しいものから最大`max_entries`件まで覚えておく。
+#[derive(Debug)]
+pub struct CookieJournal {
+    max_entries: usize,
+    entries: Mutex<VecDeque<JournalEntry>>,
+}
+
+impl CookieJournal {
+    pub fn new(config: &CookieJournalConfig) -> Option<Self> {
+        (config.max_entries > 0).then(|| Self {
+            max_entries: config.max_entries,
+            entries: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// `store`に保存する前に呼び、保存した後に`record`に渡す。
+    pub fn before(
+        &self,
+        store: &reqwest_cookie_store::CookieStore,
+        set_cookie: &str,
+        url: &reqwest::Url,
+    ) -> Option<PendingChange> {
+        let cookie = cookie::Cookie::parse(set_cookie).ok()?;
+        let domain = match cookie.domain() {
+            Some(v) => v.trim_start_matches('.').to_ascii_lowercase(),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let path = match cookie.path() {
+            Some(v) if v.starts_with('/') => v.to_string(),
+            _ => default_path(url).to_string(),
+        };
+
+        let now = cookie::time::OffsetDateTime::now_utc();
+        let existed = store.get(&domain, &path, cookie.name()).is_some();
+        let change = match expiry_change(&cookie, now) {
+            Some(change) => change,
+            None if existed => CookieChange::Overwrite,
+            None => CookieChange::Set,
+        };
+
+        Some(PendingChange {
+            change,
+            domain,
+            path,
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            existed,
+            url: url.to_string(),
+        })
+    }
+
+    /// `store`が受け入れた変更だけを記録する。domainやsecureの検査で捨てられたものは記録しない。
+    pub fn record(
+        &self,
+        store: &reqwest_cookie_store::CookieStore,
+        pending: PendingChange,
+        request_id: Option<u64>,
+    ) {
+        let stored = store.get(&pending.domain, &pending.path, &pending.name);
+        let accepted = match pending.change {
+            CookieChange::Set | CookieChange::Overwrite => {
+                stored.is_some_and(|c| c.value() == pending.value)
+            }
+            CookieChange::Delete | CookieChange::Expire => pending.existed && stored.is_none(),
+        };
+        if !accepted {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(JournalEntry {
+            timestamp,
+            change: pending.change,
+            domain: pending.domain,
+            path: pending.path,
+            name: pending.name,
+            url: pending.url,
+            request_id,
+        });
+    }
+
+    /// 新しいものから最大`count`件を返す。
+    pub fn recent(&self, count: usize) -> Vec<JournalEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().rev().take(count).cloned().collect()
+    }
+}
+
+fn expiry_change(
+    cookie: &cookie::Cookie,
+    now: cookie::time::OffsetDateTime,
+) -> Option<CookieChange> {
+    if let Some(max_age) = cookie.max_age() {
+        return (!max_age.is_positive()).then_some(CookieChange::Delete);
+    }
+    match cookie.expires_datetime() {
+        Some(expires) if expires <= now => Some(CookieChange::Expire),
+        _ => None,
+    }
+}
+
+/// RFC 6265 5.1.4のdefault-path
+fn default_path(url: &reqwest::Url) -> &str {
+    match url.path().rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &url.path()[..i],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(set_cookie: &str) -> Option<CookieChange> {
+        let parsed = cookie::Cookie::parse(set_cookie).unwrap();
+        expiry_change(&parsed, cookie::time::OffsetDateTime::now_utc())
+    }
+
+    #[test]
+    fn classifies_expiry() {
+        assert_eq!(change("a=1; Max-Age=0"), Some(CookieChange::Delete));
+        assert_eq!(
+            change("a=1; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(CookieChange::Expire)
+        );
+        assert_eq!(change("a=1; Max-Age=60"), None);
+        assert_eq!(change("a=1"), None);
+    }
+
+    #[test]
+    fn records_only_accepted_cookies() {
+        let journal = CookieJournal::new(&CookieJournalConfig { max_entries: 8 }).unwrap();
+        let mut store = reqwest_cookie_store::CookieStore::new(None);
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        for header in ["a=1", "b=2; Domain=other.example", "c=3; Max-Age=0"] {
+            let pending = journal.before(&store, header, &url);
+            let _ = store.parse(header, &url);
+            if let Some(pending) = pending {
+                journal.record(&store, pending, Some(7));
+            }
+        }
+
+        let entries = journal.recent(8);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a");
+        assert_eq!(entries[0].change, CookieChange::Set);
+        assert_eq!(entries[0].request_id, Some(7));
+    }
+
+    #[test]
+    fn default_paths() {
+        let path = |s| default_path(&reqwest::Url::parse(s).unwrap()).to_string();
+        assert_eq!(path("https://example.com"), "/");
+        assert_eq!(path("https://example.com/login"), "/");
+        assert_eq!(path("https://example.com/a/b/login"), "/a/b");
+    }
+}
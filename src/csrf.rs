@@ -0,0 +1,41 @@
+use crate::{bridge::cookie_value, CookieClient};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::HashMap;
+
+/// `url`にマッチするリクエストで、cookie `cookie`の値をヘッダー`header`として送る。
+#[serde_with::serde_as]
+#[derive(Debug, serde::Deserialize)]
+pub struct CsrfRule {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub url: glob::Pattern,
+    pub cookie: String,
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub header: HeaderName,
+}
+
+/// ヘッダーが明示されている場合は上書きしない。
+pub fn apply(
+    rules: &[CsrfRule],
+    client: &CookieClient,
+    ephemeral_cookies: &HashMap<String, String>,
+    url: &reqwest::Url,
+    headers: &mut HeaderMap,
+) {
+    for rule in rules {
+        if !rule.url.matches(url.as_str()) || headers.contains_key(&rule.header) {
+            continue;
+        }
+        let value = cookie_value(client, ephemeral_cookies, url, &rule.cookie);
+        // Laravelなどはcookieの値をURLエンコードして送る
+        let Some(value) = value.map(|v| {
+            percent_encoding::percent_decode_str(&v)
+                .decode_utf8_lossy()
+                .into_owned()
+        }) else {
+            continue;
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(rule.header.clone(), value);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::cookie_fetch::FetchError;
+use reqwest::{ResponseBuilderExt, StatusCode};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// 開発用に、`pattern`にマッチするURLへのリクエストで障害を起こす。`Builder::fault`で登録する。
+/// 最初にマッチしたものだけが、送信(再送を含む)のたびに適用される。
+#[derive(Debug, Clone)]
+pub struct Fault {
+    pub(crate) pattern: glob::Pattern,
+    pub(crate) latency: Option<Duration>,
+    pub(crate) failure_rate: f64,
+    pub(crate) status: Option<(StatusCode, f64)>,
+}
+
+impl Fault {
+    pub fn new(pattern: glob::Pattern) -> Self {
+        Self {
+            pattern,
+            latency: None,
+            failure_rate: 0.0,
+            status: None,
+        }
+    }
+
+    /// 送る前に待つ時間
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// `rate`の確率で、送らずに接続の失敗にする。
+    pub fn failure_rate(mut self, rate: f64) -> Self {
+        self.failure_rate = rate;
+        self
+    }
+
+    /// `rate`の確率で、送らずに空のボディの`status`を返す。
+    pub fn status(mut self, status: StatusCode, rate: f64) -> Self {
+        self.status = Some((status, rate));
+        self
+    }
+}
+
+/// 障害を起こした場合はその結果を返す。
+pub async fn inject(
+    faults: &[Fault],
+    request: &reqwest::Request,
+) -> Option<Result<reqwest::Response, FetchError>> {
+    let fault = faults
+        .iter()
+        .find(|f| f.pattern.matches(request.url().as_str()))?;
+
+    if let Some(latency) = fault.latency {
+        tokio::time::sleep(latency).await;
+    }
+    if roll(fault.failure_rate) {
+        return Some(Err(FetchError::InjectedFault));
+    }
+    match fault.status {
+        Some((status, rate)) if roll(rate) => {
+            let res = http::Response::builder()
+                .status(status)
+                .url(request.url().clone())
+                .body(Vec::new())
+                .unwrap();
+            Some(Ok(reqwest::Response::from(res)))
+        }
+        _ => None,
+    }
+}
+
+fn roll(rate: f64) -> bool {
+    if rate <= 0.0 {
+        return false;
+    }
+    let random = RandomState::new().build_hasher().finish();
+    (random as f64 / u64::MAX as f64) < rate
+}
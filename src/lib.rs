@@ -1,17 +1,73 @@
+mod aborts;
+mod bridge;
 mod config;
 mod cookie_fetch;
+mod cookie_journal;
+mod csrf;
+mod logger;
+mod network;
+mod pending;
+mod pool_stats;
+mod proxy;
+mod readers;
+mod redaction;
+mod registry;
+mod rewrite;
 mod scope;
+mod snapshot;
+mod sockets;
 mod state;
+mod subscriptions;
+mod throttle;
+mod uploads;
 
+pub mod auth_provider;
+pub mod body_provider;
+pub mod body_transform;
 pub mod cookie_client;
+pub mod fault;
+pub mod preset;
+pub mod request_signer;
 
-use cookie_client::{CookieClient, CookieClientPool, RedirectPolicy};
-use cookie_fetch::{FetchOptions, Response};
+use aborts::Aborts;
+use auth_provider::AuthProvider;
+use body_provider::BodyProvider;
+use body_transform::{RequestTransform, ResponseTransform};
+use cookie_client::{
+    ClientSettings, CookieClient, CookieClientPool, RedirectGuard, RedirectPolicy, TlsBackend,
+};
+use cookie_fetch::{
+    AuthReply, BatchRequest, BatchResult, Body, ChainStep, ConfigPatch, Consent, EffectiveConfig,
+    ErrorReport, FetchError, FetchOptions, HeartbeatOptions, InterceptReply, MemoryCache, Outbox,
+    OutboxItem, OutboxRequest, Payload, PrefetchOptions, Probe, RangeResponse, RedirectReply,
+    Response, ResponseHead, RuntimeConfig, RuntimeSettings, ScheduleOptions,
+};
+use cookie_journal::{CookieJournal, JournalEntry};
+use fault::Fault;
+use logger::{LogEntry, Logger};
+use network::NetworkStatus;
+use pending::Pending;
+use pool_stats::{PoolStats, PoolStatus};
+use preset::Preset;
+use readers::Readers;
+use registry::Registry;
+use request_signer::RequestSigner;
+use rewrite::Rewrite;
+use sockets::Sockets;
 use state::CookieFetchState;
+use std::sync::Arc;
+use subscriptions::Subscriptions;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_bin_ipc::{
     bin_command, generate_bin_handler, BinIpcError, PluginBuilderBinIpcExtension,
 };
+use throttle::Bandwidth;
+use uploads::Uploads;
+
+/// JSでは`kind`やステータスを値として読めるよう、構造化したエラーとして渡す。
+fn report(e: FetchError) -> BinIpcError {
+    BinIpcError::new_reportable(ErrorReport::from(e))
+}
 
 #[bin_command]
 async fn fetch<R: tauri::Runtime>(
@@ -19,25 +75,578 @@ async fn fetch<R: tauri::Runtime>(
     url: String,
     options: Option<FetchOptions>,
 ) -> Result<Response, BinIpcError> {
-    let res = cookie_fetch::fetch(app, url, options)
+    let res = cookie_fetch::fetch(app, "fetch", url, options)
         .await
-        .map_err(BinIpcError::new_reportable)?;
+        .map_err(report)?;
 
     Ok(res)
 }
 
+#[bin_command]
+async fn fetch_preset<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    preset: String,
+    path: String,
+    options: Option<FetchOptions>,
+) -> Result<Response, BinIpcError> {
+    cookie_fetch::fetch_preset(app, preset, path, options)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn fetch_range<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    start: u64,
+    end: Option<u64>,
+    options: Option<FetchOptions>,
+) -> Result<RangeResponse, BinIpcError> {
+    cookie_fetch::fetch_range(app, url, start, end, options)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn probe<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Probe, BinIpcError> {
+    cookie_fetch::probe(app, url, options).await.map_err(report)
+}
+
+#[bin_command]
+async fn fetch_batch<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    requests: Vec<BatchRequest>,
+    concurrency: Option<usize>,
+) -> Result<Vec<BatchResult>, BinIpcError> {
+    Ok(cookie_fetch::fetch_batch(app, requests, concurrency).await)
+}
+
+#[bin_command]
+async fn fetch_chain<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    steps: Vec<ChainStep>,
+) -> Result<Vec<Response>, BinIpcError> {
+    cookie_fetch::fetch_chain(app, steps).await.map_err(report)
+}
+
+#[bin_command]
+async fn sse<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::sse(app, url, options, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn ndjson<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::ndjson(app, url, options, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn heartbeat<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    heartbeat: HeartbeatOptions,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::heartbeat(app, url, options, heartbeat, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn schedule<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    schedule: ScheduleOptions,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::schedule(app, url, options, schedule, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn outbox_enqueue<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    request: OutboxRequest,
+) -> Result<u64, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    state.outbox.enqueue(&state, request).map_err(report)
+}
+
+#[bin_command]
+async fn outbox_items<R: tauri::Runtime>(
+    app: AppHandle<R>,
+) -> Result<Vec<OutboxItem>, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.outbox.items())
+}
+
+#[bin_command]
+async fn outbox_remove<R: tauri::Runtime>(app: AppHandle<R>, id: u64) -> Result<bool, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.outbox.remove(id))
+}
+
+#[bin_command]
+async fn cookie_journal<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    count: usize,
+) -> Result<Vec<JournalEntry>, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(match &state.cookie_journal {
+        Some(journal) => journal.recent(count),
+        None => Vec::new(),
+    })
+}
+
+/// `host`を省略すると全てのホストについて忘れる。
+#[bin_command]
+async fn forget_consent<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    host: Option<String>,
+) -> Result<bool, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.consent.forget(host.as_deref()))
+}
+
+/// 設定の`allow_set_config`が必要。
+#[bin_command]
+async fn set_config<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    patch: ConfigPatch,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    if !state.config.allow_set_config {
+        return Err(report(FetchError::ConfigLocked));
+    }
+    state
+        .runtime_config
+        .update(patch, &state.client_pool)
+        .map_err(report)
+}
+
+#[bin_command]
+async fn get_config<R: tauri::Runtime>(app: AppHandle<R>) -> Result<EffectiveConfig, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.runtime_config.effective(&state.config.redaction))
+}
+
+#[bin_command]
+async fn unsubscribe<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    state.subscriptions.unsubscribe(&channel);
+    Ok(())
+}
+
+#[bin_command]
+async fn ws_connect<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::ws_connect(app, url, options, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn ws_send<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    channel: String,
+    data: Payload,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::ws_send(&state, &channel, data).map_err(report)
+}
+
+#[bin_command]
+async fn ws_close<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    channel: String,
+    code: Option<u16>,
+    reason: Option<String>,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::ws_close(&state, &channel, code, reason).map_err(report)
+}
+
+#[bin_command]
+async fn grpc_web<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    message: Body,
+    options: Option<FetchOptions>,
+    channel: String,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::grpc_web(app, url, message, options, channel)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn fetch_begin<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<ResponseHead, BinIpcError> {
+    cookie_fetch::fetch_begin(app, url, options)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn read_chunk<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    rid: u64,
+    max: usize,
+) -> Result<Option<Payload>, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::read_chunk(&state, rid, max)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn close_body<R: tauri::Runtime>(app: AppHandle<R>, rid: u64) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::close_body(&state, rid);
+    Ok(())
+}
+
+#[bin_command]
+async fn upload_begin<R: tauri::Runtime>(app: AppHandle<R>) -> Result<u64, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.uploads.begin())
+}
+
+#[bin_command]
+async fn upload_chunk<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    id: u64,
+    data: Payload,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::upload_chunk(&state, id, data)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn upload_end<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    id: u64,
+    cancel: bool,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::upload_end(&state, id, cancel).await;
+    Ok(())
+}
+
+#[bin_command]
+async fn intercept_reply<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    id: u64,
+    reply: InterceptReply,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    state.interceptors.reply(id, reply);
+    Ok(())
+}
+
+#[bin_command]
+async fn redirect_reply<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    id: u64,
+    reply: RedirectReply,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    state.redirects.reply(id, reply);
+    Ok(())
+}
+
+#[bin_command]
+async fn auth_reply<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    id: u64,
+    reply: AuthReply,
+) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    state.auth_challenges.reply(id, reply);
+    Ok(())
+}
+
+#[bin_command]
+async fn recent_logs<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    count: usize,
+) -> Result<Vec<LogEntry>, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.logger.recent(count).await)
+}
+
+#[bin_command]
+async fn is_offline<R: tauri::Runtime>(app: AppHandle<R>) -> Result<bool, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.network.is_offline())
+}
+
+#[bin_command]
+async fn abort_by_tag<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    tag: String,
+) -> Result<usize, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    Ok(state.aborts.abort(&tag) + state.readers.abort(&tag) + state.outbox.abort(&tag))
+}
+
+#[bin_command]
+async fn preconnect<R: tauri::Runtime>(app: AppHandle<R>, url: String) -> Result<(), BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    cookie_fetch::preconnect(&state, url).await.map_err(report)
+}
+
+#[bin_command]
+async fn prefetch<R: tauri::Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    options: Option<FetchOptions>,
+    prefetch: PrefetchOptions,
+) -> Result<(), BinIpcError> {
+    cookie_fetch::prefetch(app, url, options, prefetch)
+        .await
+        .map_err(report)
+}
+
+#[bin_command]
+async fn pool_status<R: tauri::Runtime>(app: AppHandle<R>) -> Result<PoolStatus, BinIpcError> {
+    let state = app.state::<CookieFetchState>();
+    let status = state.client_pool.status();
+    Ok(PoolStatus {
+        clients: status.size,
+        idle_clients: status.available,
+        max_clients: status.max_size,
+        origins: state.pool_stats.origins(),
+        tags: state.pool_stats.tags(),
+    })
+}
+
 const PLUGIN_NAME: &str = "cookie-fetch";
 
+#[derive(Default)]
+pub struct Builder {
+    registry: Registry,
+    tls_backend: Option<TlsBackend>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn body_provider<P: BodyProvider + 'static>(
+        mut self,
+        name: impl Into<String>,
+        provider: P,
+    ) -> Self {
+        self.registry
+            .body_providers
+            .insert(name.into(), Arc::new(provider));
+        self
+    }
+
+    /// 開発用。登録した順にURLと照合する。
+    pub fn fault(mut self, fault: Fault) -> Self {
+        self.registry.faults.push(fault);
+        self
+    }
+
+    pub fn auth_provider<P: AuthProvider + 'static>(mut self, provider: P) -> Self {
+        self.registry.auth_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// `from`で始まるURLを、その部分を`to`に置き換えて送る。scopeは置き換える前のURLで確認する。
+    pub fn rewrite(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.registry.rewrites.push(Rewrite {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// HTTPのリクエストに使うTLSの実装。WebSocketはtokio-tungsteniteのfeatureで決まる。
+    pub fn tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.tls_backend = Some(backend);
+        self
+    }
+
+    /// ホストが`pattern`にマッチするリクエストに署名する。
+    pub fn request_signer<T: RequestSigner + 'static>(
+        mut self,
+        pattern: glob::Pattern,
+        signer: T,
+    ) -> Self {
+        self.registry
+            .request_signers
+            .push((pattern, Arc::new(signer)));
+        self
+    }
+
+    /// `pattern`にマッチするURLへのリクエストのボディを変換する。
+    pub fn request_transform<T: RequestTransform + 'static>(
+        mut self,
+        pattern: glob::Pattern,
+        transform: T,
+    ) -> Self {
+        self.registry
+            .request_transforms
+            .push((pattern, Arc::new(transform)));
+        self
+    }
+
+    /// `pattern`にマッチするURLのレスポンスのボディを変換する。
+    pub fn response_transform<T: ResponseTransform + 'static>(
+        mut self,
+        pattern: glob::Pattern,
+        transform: T,
+    ) -> Self {
+        self.registry
+            .response_transforms
+            .push((pattern, Arc::new(transform)));
+        self
+    }
+
+    pub fn preset(mut self, name: impl Into<String>, preset: Preset) -> Self {
+        self.registry.presets.insert(name.into(), preset);
+        self
+    }
+
+    pub fn build<R: tauri::Runtime>(self) -> tauri::plugin::TauriPlugin<R, config::Config> {
+        let registry = self.registry;
+        let tls_backend = self.tls_backend;
+        tauri::plugin::Builder::new(PLUGIN_NAME)
+            .bin_ipc_handler(
+                PLUGIN_NAME,
+                generate_bin_handler![
+                    fetch,
+                    sse,
+                    ndjson,
+                    unsubscribe,
+                    ws_connect,
+                    ws_send,
+                    ws_close,
+                    grpc_web,
+                    fetch_begin,
+                    read_chunk,
+                    close_body,
+                    recent_logs,
+                    intercept_reply,
+                    redirect_reply,
+                    auth_reply,
+                    upload_begin,
+                    upload_chunk,
+                    upload_end,
+                    is_offline,
+                    preconnect,
+                    prefetch,
+                    fetch_preset,
+                    fetch_batch,
+                    fetch_chain,
+                    pool_status,
+                    abort_by_tag,
+                    fetch_range,
+                    probe,
+                    heartbeat,
+                    schedule,
+                    outbox_enqueue,
+                    outbox_items,
+                    outbox_remove,
+                    cookie_journal,
+                    forget_consent,
+                    set_config,
+                    get_config
+                ],
+            )
+            .setup_with_config(move |app, config| {
+                let proxies = config
+                    .proxies
+                    .iter()
+                    .map(|p| p.to_reqwest())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let logger = Logger::new(
+                    &config.log,
+                    &config.redaction,
+                    app.path_resolver().app_data_dir(),
+                );
+                let cookie_journal = CookieJournal::new(&config.cookie_journal).map(Arc::new);
+                app.manage(CookieFetchState {
+                    client_pool: CookieClientPool::with_config(
+                        ClientSettings {
+                            proxies,
+                            connect_timeout: None,
+                            http2: config.http2.clone(),
+                            tls_backend,
+                            journal: cookie_journal.clone(),
+                        },
+                        &config.pool,
+                    ),
+                    subscriptions: Subscriptions::new(),
+                    sockets: Sockets::new(),
+                    readers: Readers::new(),
+                    registry,
+                    logger,
+                    interceptors: Pending::new(),
+                    redirects: Pending::new(),
+                    auth_challenges: Pending::new(),
+                    uploads: Uploads::new(),
+                    network: NetworkStatus::new(),
+                    pool_stats: PoolStats::new(),
+                    aborts: Aborts::new(),
+                    bandwidth: Bandwidth::new(&config.bandwidth),
+                    outbox: Outbox::load(app.path_resolver().app_data_dir()),
+                    memory_cache: MemoryCache::new(&config.memory_cache),
+                    cookie_journal,
+                    runtime_config: RuntimeConfig::new(RuntimeSettings {
+                        proxies: config.proxies.clone(),
+                        ..Default::default()
+                    }),
+                    consent: Arc::new(Consent::load(
+                        &config.consent,
+                        app.path_resolver().app_data_dir(),
+                    )),
+                    config,
+                });
+                tauri::async_runtime::spawn(cookie_fetch::flush_outbox(app.clone()));
+
+                Ok(())
+            })
+            .build()
+    }
+}
+
 pub fn init<R: tauri::Runtime>() -> tauri::plugin::TauriPlugin<R, config::Config> {
-    tauri::plugin::Builder::new(PLUGIN_NAME)
-        .bin_ipc_handler(PLUGIN_NAME, generate_bin_handler![fetch])
-        .setup_with_config(|app, config| {
-            app.manage(CookieFetchState {
-                client_pool: CookieClientPool::new(),
-                config,
-            });
-
-            Ok(())
-        })
-        .build()
+    Builder::new().build()
 }
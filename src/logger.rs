@@ -0,0 +1,350 @@
+use crate::{cookie_fetch::FetchError, redaction::Redaction};
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{mpsc, Mutex};
+
+const LOG_FILE_NAME: &str = "cookie-fetch.log";
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    /// ローテーションで残す古いファイルの数
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// ヘッダーとボディの先頭も記録するURLのパターン
+    #[serde(default, deserialize_with = "crate::scope::deserialize_patterns")]
+    pub verbose: Vec<glob::Pattern>,
+    #[serde(default = "default_preview_length")]
+    pub preview_length: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_file_size: default_max_file_size(),
+            max_files: default_max_files(),
+            verbose: Vec::new(),
+            preview_length: default_preview_length(),
+        }
+    }
+}
+
+fn default_max_file_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    3
+}
+
+fn default_preview_length() -> usize {
+    256
+}
+
+type LogHeaders = BTreeMap<String, Vec<String>>;
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// 起動ごとに1から振る、リクエストのid
+    #[serde(default)]
+    pub id: u64,
+    /// UNIX時間(ミリ秒)
+    pub timestamp: u64,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub status: Option<u16>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub request_size: Option<u64>,
+    #[serde(default)]
+    pub response_size: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub request_headers: Option<LogHeaders>,
+    #[serde(default)]
+    pub response_headers: Option<LogHeaders>,
+    #[serde(default)]
+    pub response_preview: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+pub struct PendingLog {
+    id: u64,
+    timestamp: u64,
+    started: Instant,
+    method: String,
+    url: String,
+    request_size: Option<u64>,
+    verbose: bool,
+    request_headers: Option<LogHeaders>,
+    response_headers: Option<LogHeaders>,
+    response_preview: Option<String>,
+    tags: Vec<String>,
+}
+
+impl PendingLog {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn finish(
+        self,
+        status: Option<u16>,
+        response_size: Option<u64>,
+        error: Option<&FetchError>,
+    ) -> LogEntry {
+        let status = status.or(match error {
+            Some(FetchError::Status { status, .. }) => Some(*status),
+            _ => None,
+        });
+
+        LogEntry {
+            id: self.id,
+            timestamp: self.timestamp,
+            method: self.method,
+            url: self.url,
+            status,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            request_size: self.request_size,
+            response_size,
+            error: error.map(|e| e.kind().to_string()),
+            request_headers: self.request_headers,
+            response_headers: self.response_headers,
+            response_preview: self.response_preview,
+            tags: self.tags,
+        }
+    }
+}
+
+/// 1リクエスト1行のJSONを`max_file_size`でローテーションしながら書き込む。
+/// 書き込みはバックグラウンドのタスクで行い、リクエストを待たせない。
+pub struct Logger {
+    files: Option<Arc<LogFiles>>,
+    lines: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    verbose: Vec<glob::Pattern>,
+    preview_length: usize,
+    redaction: Redaction,
+    next_id: AtomicU64,
+}
+
+impl Logger {
+    pub fn new(config: &LogConfig, redaction: &Redaction, dir: Option<PathBuf>) -> Self {
+        let files = dir.filter(|_| config.enabled).map(|dir| {
+            Arc::new(LogFiles {
+                path: dir.join(LOG_FILE_NAME),
+                max_file_size: config.max_file_size,
+                max_files: config.max_files,
+                lock: Mutex::new(()),
+            })
+        });
+        let lines = files.clone().map(|files| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tauri::async_runtime::spawn(files.run(rx));
+            tx
+        });
+
+        Self {
+            files,
+            lines,
+            verbose: config.verbose.clone(),
+            preview_length: config.preview_length,
+            redaction: redaction.clone(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn begin(&self, request: &reqwest::Request, tags: &[String]) -> PendingLog {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let verbose = self.files.is_some()
+            && self
+                .verbose
+                .iter()
+                .any(|pat| pat.matches(request.url().as_str()));
+
+        PendingLog {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp,
+            started: Instant::now(),
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            request_size: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| b.len() as u64),
+            verbose,
+            request_headers: verbose.then(|| self.log_headers(request.headers())),
+            response_headers: None,
+            response_preview: None,
+            tags: tags.to_vec(),
+        }
+    }
+
+    /// `verbose`にマッチしたリクエストの場合のみ記録する。
+    pub fn response(
+        &self,
+        log: &mut PendingLog,
+        headers: &reqwest::header::HeaderMap,
+        body: Option<&[u8]>,
+    ) {
+        if !log.verbose {
+            return;
+        }
+
+        log.response_headers = Some(self.log_headers(headers));
+        log.response_preview = body.map(|b| {
+            let len = b.len().min(self.preview_length);
+            String::from_utf8_lossy(&b[..len]).into_owned()
+        });
+    }
+
+    fn log_headers(&self, headers: &reqwest::header::HeaderMap) -> LogHeaders {
+        let mut headers = headers.clone();
+        self.redaction.apply(&mut headers);
+
+        let mut map = LogHeaders::new();
+        for (name, value) in headers.iter() {
+            map.entry(name.to_string())
+                .or_default()
+                .push(String::from_utf8_lossy(value.as_bytes()).into_owned());
+        }
+        map
+    }
+
+    /// 書き込みに失敗してもリクエストには影響させない。
+    pub fn write(&self, entry: &LogEntry) {
+        let Some(lines) = &self.lines else {
+            return;
+        };
+        let Ok(mut line) = serde_json::to_vec(entry) else {
+            return;
+        };
+        line.push(b'\n');
+        let _ = lines.send(line);
+    }
+
+    /// 新しいものから最大`count`件を返す。書き込みを待っている行は含まない。
+    pub async fn recent(&self, count: usize) -> Vec<LogEntry> {
+        let Some(files) = &self.files else {
+            return Vec::new();
+        };
+
+        let _guard = files.lock.lock().await;
+        let read = tauri::async_runtime::spawn_blocking({
+            let files = Arc::clone(files);
+            move || files.recent(count)
+        });
+        read.await.unwrap_or_default()
+    }
+}
+
+struct LogFiles {
+    path: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    /// 書き込みとローテーションの途中を読まないようにする。
+    lock: Mutex<()>,
+}
+
+impl LogFiles {
+    /// 溜まった行をまとめて書き込む。ファイルの操作はブロックするため別のスレッドで行う。
+    async fn run(self: Arc<Self>, mut rx: mpsc::UnboundedReceiver<Vec<u8>>) {
+        while let Some(line) = rx.recv().await {
+            let mut lines = vec![line];
+            while let Ok(line) = rx.try_recv() {
+                lines.push(line);
+            }
+
+            let _guard = self.lock.lock().await;
+            let files = Arc::clone(&self);
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                for line in lines {
+                    files.append(&line);
+                }
+            })
+            .await;
+        }
+    }
+
+    fn append(&self, line: &[u8]) {
+        let path = &self.path;
+        if let Ok(meta) = std::fs::metadata(path) {
+            if meta.len() + line.len() as u64 > self.max_file_size {
+                self.rotate(path);
+            }
+        } else if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = file.write_all(line);
+        }
+    }
+
+    fn rotate(&self, path: &Path) {
+        if self.max_files == 0 {
+            let _ = std::fs::remove_file(path);
+            return;
+        }
+
+        let _ = std::fs::remove_file(rotated(path, self.max_files));
+        for i in (1..self.max_files).rev() {
+            let _ = std::fs::rename(rotated(path, i), rotated(path, i + 1));
+        }
+        let _ = std::fs::rename(path, rotated(path, 1));
+    }
+
+    fn recent(&self, count: usize) -> Vec<LogEntry> {
+        let path = &self.path;
+        let mut entries = Vec::new();
+        let files = std::iter::once(path.to_path_buf())
+            .chain((1..=self.max_files).map(|i| rotated(path, i)));
+        for file in files {
+            if entries.len() >= count {
+                break;
+            }
+            let Ok(file) = std::fs::File::open(file) else {
+                break;
+            };
+
+            let mut lines: Vec<LogEntry> = BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|l| serde_json::from_str(&l).ok())
+                .collect();
+            lines.reverse();
+            entries.extend(lines);
+        }
+
+        entries.truncate(count);
+        entries
+    }
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Manager;
+
+const NETWORK_STATUS_EVENT: &str = "cookie-fetch://network-status";
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkStatusEvent {
+    offline: bool,
+}
+
+/// 最後のリクエストの結果から推定したネットワークの状態
+pub struct NetworkStatus {
+    offline: AtomicBool,
+    online: tokio::sync::Notify,
+}
+
+impl NetworkStatus {
+    pub fn new() -> Self {
+        Self {
+            offline: AtomicBool::new(false),
+            online: tokio::sync::Notify::new(),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    /// 状態が変わった場合のみ`cookie-fetch://network-status`を送る。
+    pub fn report<R: tauri::Runtime>(&self, app: &tauri::AppHandle<R>, offline: bool) {
+        if self.offline.swap(offline, Ordering::Relaxed) != offline {
+            let _ = app.emit_all(NETWORK_STATUS_EVENT, NetworkStatusEvent { offline });
+            if !offline {
+                self.online.notify_waiters();
+            }
+        }
+    }
+
+    /// オフラインと推定されている間、戻ったと分かるまで待つ。
+    pub async fn online(&self) {
+        let online = self.online.notified();
+        if self.is_offline() {
+            online.await;
+        }
+    }
+}
+
+// ENETUNREACH, EHOSTUNREACH
+#[cfg(target_os = "linux")]
+const UNREACHABLE: [i32; 2] = [101, 113];
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const UNREACHABLE: [i32; 2] = [51, 65];
+#[cfg(windows)]
+const UNREACHABLE: [i32; 2] = [10051, 10065];
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "ios", windows)))]
+const UNREACHABLE: [i32; 0] = [];
+
+/// この接続のローカルのアドレス。reqwestは`remote_addr`しか公開しないため、hyperの`HttpInfo`から読む。
+pub fn local_addr(res: &reqwest::Response) -> Option<std::net::SocketAddr> {
+    res.extensions()
+        .get::<hyper::client::connect::HttpInfo>()
+        .map(|info| info.local_addr())
+}
+
+/// 経路がないために接続できなかった場合。サーバーが接続を拒否した場合は含まない。
+pub fn is_unreachable(e: &reqwest::Error) -> bool {
+    if !e.is_connect() {
+        return false;
+    }
+
+    let mut source = std::error::Error::source(e);
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<std::io::Error>() {
+            return e
+                .raw_os_error()
+                .is_some_and(|code| UNREACHABLE.contains(&code));
+        }
+        source = e.source();
+    }
+    false
+}
@@ -0,0 +1,42 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::oneshot;
+
+/// フロントエンドからの応答を待っている呼び出し
+pub struct Pending<T> {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, oneshot::Sender<T>>>,
+}
+
+impl<T> Pending<T> {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn insert(&self) -> (u64, oneshot::Receiver<T>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        (id, rx)
+    }
+
+    pub fn remove(&self, id: u64) {
+        self.pending.lock().unwrap().remove(&id);
+    }
+
+    /// 待っている呼び出しがなければ`false`を返す。
+    pub fn reply(&self, id: u64, reply: T) -> bool {
+        let Some(tx) = self.pending.lock().unwrap().remove(&id) else {
+            return false;
+        };
+        tx.send(reply).is_ok()
+    }
+}
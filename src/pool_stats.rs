@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// reqwestはコネクションプールの中身を公開しないため、送ったリクエストから集計する。
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginStats {
+    pub in_flight: u64,
+    pub requests: u64,
+    pub errors: u64,
+    /// 最後に受け取ったレスポンスのHTTPのバージョン
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStatus {
+    /// 作られたclientの数。clientはそれぞれ自分のコネクションプールを持つ。
+    pub clients: usize,
+    pub idle_clients: usize,
+    pub max_clients: usize,
+    pub origins: HashMap<String, OriginStats>,
+    /// `FetchOptions`の`tags`ごとの集計
+    pub tags: HashMap<String, OriginStats>,
+}
+
+#[derive(Default)]
+struct Stats {
+    origins: HashMap<String, OriginStats>,
+    tags: HashMap<String, OriginStats>,
+}
+
+impl Stats {
+    fn each(&mut self, origin: &str, tags: &[String], mut f: impl FnMut(&mut OriginStats)) {
+        if let Some(stats) = self.origins.get_mut(origin) {
+            f(stats);
+        }
+        for tag in tags {
+            if let Some(stats) = self.tags.get_mut(tag) {
+                f(stats);
+            }
+        }
+    }
+}
+
+pub struct PoolStats {
+    stats: Arc<Mutex<Stats>>,
+}
+
+impl PoolStats {
+    pub fn new() -> Self {
+        Self {
+            stats: Arc::new(Mutex::new(Stats::default())),
+        }
+    }
+
+    pub fn begin(&self, url: &reqwest::Url, tags: &[String]) -> InFlight {
+        let origin = url.origin().ascii_serialization();
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.origins.entry(origin.clone()).or_default();
+            for tag in tags {
+                stats.tags.entry(tag.clone()).or_default();
+            }
+            stats.each(&origin, tags, |s| {
+                s.in_flight += 1;
+                s.requests += 1;
+            });
+        }
+
+        InFlight {
+            stats: Arc::clone(&self.stats),
+            origin,
+            tags: tags.to_vec(),
+        }
+    }
+
+    pub fn origins(&self) -> HashMap<String, OriginStats> {
+        self.stats.lock().unwrap().origins.clone()
+    }
+
+    pub fn tags(&self) -> HashMap<String, OriginStats> {
+        self.stats.lock().unwrap().tags.clone()
+    }
+}
+
+/// 送信中のリクエスト。dropで`in_flight`から外れる。
+pub struct InFlight {
+    stats: Arc<Mutex<Stats>>,
+    origin: String,
+    tags: Vec<String>,
+}
+
+impl InFlight {
+    pub fn finish<E>(self, result: &Result<reqwest::Response, E>) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.each(&self.origin, &self.tags, |s| match result {
+            Ok(res) => s.version = Some(format!("{:?}", res.version())),
+            Err(_) => s.errors += 1,
+        });
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.stats
+            .lock()
+            .unwrap()
+            .each(&self.origin, &self.tags, |s| {
+                s.in_flight = s.in_flight.saturating_sub(1)
+            });
+    }
+}
@@ -0,0 +1,50 @@
+use crate::auth_provider::Credentials;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+
+/// `fetchPreset`から名前で参照されるリクエストの既定値。`Builder::preset`で登録する。
+/// ヘッダーや認証情報はwebviewに渡らない。
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub(crate) base_url: reqwest::Url,
+    pub(crate) headers: HeaderMap,
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl Preset {
+    pub fn new(base_url: reqwest::Url) -> Self {
+        Self {
+            base_url,
+            headers: HeaderMap::new(),
+            credentials: None,
+            timeout: None,
+        }
+    }
+
+    /// リクエストで同じ名前のヘッダーが指定された場合はそちらが優先される。
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// `Authorization`ヘッダーとして送る。
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// `path`を`base_url`のパスに続ける。
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.as_str().trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+}
@@ -0,0 +1,51 @@
+use crate::body_provider::BoxError;
+
+/// 設定したプロキシは環境変数のプロキシより優先される。
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    pub url: String,
+    /// このプロキシを使うURLのパターン。空の場合は全て。
+    #[serde(default, deserialize_with = "crate::scope::deserialize_patterns")]
+    pub targets: Vec<glob::Pattern>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    pub fn matches(&self, url: &reqwest::Url) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|pat| pat.matches(url.as_str()))
+    }
+
+    /// 認証情報を除いたプロキシのURL
+    pub fn display_url(&self) -> Option<String> {
+        let mut url = reqwest::Url::parse(&self.url).ok()?;
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        Some(url.to_string())
+    }
+
+    pub fn to_reqwest(&self) -> Result<reqwest::Proxy, BoxError> {
+        let proxy = if self.targets.is_empty() {
+            reqwest::Proxy::all(self.url.as_str())?
+        } else {
+            let proxy_url = reqwest::Url::parse(&self.url)?;
+            let targets = self.targets.clone();
+            reqwest::Proxy::custom(move |url| {
+                targets
+                    .iter()
+                    .any(|pat| pat.matches(url.as_str()))
+                    .then(|| proxy_url.clone())
+            })
+        };
+
+        Ok(match &self.username {
+            Some(username) => {
+                proxy.basic_auth(username, self.password.as_deref().unwrap_or_default())
+            }
+            None => proxy,
+        })
+    }
+}
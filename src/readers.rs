@@ -0,0 +1,94 @@
+use crate::body_transform::ResponseStream;
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// この間読まれなかったreaderは、次に`insert`か`get`が呼ばれたときに解放する。
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+pub struct BodyReader {
+    pub body: ResponseStream,
+    pub pending: Bytes,
+    pub read_timeout: Option<Duration>,
+}
+
+struct Entry {
+    /// `abort_by_tag`で解放するため、readerごとにタグを持つ。
+    tags: Vec<String>,
+    reader: Arc<tokio::sync::Mutex<BodyReader>>,
+    last_used: Instant,
+}
+
+pub struct Readers {
+    next_rid: AtomicU64,
+    readers: Mutex<HashMap<u64, Entry>>,
+}
+
+impl Readers {
+    pub fn new() -> Self {
+        Self {
+            next_rid: AtomicU64::new(1),
+            readers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn insert(
+        &self,
+        body: ResponseStream,
+        read_timeout: Option<Duration>,
+        tags: Vec<String>,
+    ) -> u64 {
+        let rid = self.next_rid.fetch_add(1, Ordering::Relaxed);
+        let reader = BodyReader {
+            body,
+            pending: Bytes::new(),
+            read_timeout,
+        };
+
+        let mut readers = self.readers.lock().unwrap();
+        expire(&mut readers);
+        readers.insert(
+            rid,
+            Entry {
+                tags,
+                reader: Arc::new(tokio::sync::Mutex::new(reader)),
+                last_used: Instant::now(),
+            },
+        );
+
+        rid
+    }
+
+    pub fn get(&self, rid: u64) -> Option<Arc<tokio::sync::Mutex<BodyReader>>> {
+        let mut readers = self.readers.lock().unwrap();
+        expire(&mut readers);
+        let entry = readers.get_mut(&rid)?;
+        entry.last_used = Instant::now();
+        Some(Arc::clone(&entry.reader))
+    }
+
+    pub fn remove(&self, rid: u64) -> bool {
+        self.readers.lock().unwrap().remove(&rid).is_some()
+    }
+
+    /// `tag`の付いたreaderを解放し、その数を返す。読んでいる途中のreaderは読み終えてから捨てられる。
+    pub fn abort(&self, tag: &str) -> usize {
+        let mut readers = self.readers.lock().unwrap();
+        let len = readers.len();
+        readers.retain(|_, entry| !entry.tags.iter().any(|t| t == tag));
+        len - readers.len()
+    }
+}
+
+/// 読み捨てられたreaderが接続を持ち続けないようにする。読んでいる途中のreaderは残す。
+fn expire(readers: &mut HashMap<u64, Entry>) {
+    readers.retain(|_, entry| {
+        entry.last_used.elapsed() < IDLE_TIMEOUT || Arc::strong_count(&entry.reader) > 1
+    });
+}
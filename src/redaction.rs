@@ -0,0 +1,92 @@
+use reqwest::header::{HeaderMap, HeaderValue};
+
+const DEFAULT_HEADERS: &[&str] = &["authorization", "proxy-authorization"];
+const COOKIE_HEADERS: &[&str] = &["cookie", "set-cookie"];
+const REDACTED: &str = "[redacted]";
+
+/// ログやエラーに含めるとき値を伏せるヘッダー。
+/// Authorization、Proxy-Authorization、Cookie、Set-Cookieは常に伏せられ、`headers`はそれに追加される。
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Redaction {
+    #[serde(default)]
+    pub headers: Vec<String>,
+    /// デバッグ用。CookieとSet-Cookieの値を伏せない。
+    #[serde(default)]
+    pub reveal_cookies: bool,
+}
+
+impl Redaction {
+    pub fn is_redacted(&self, name: &str) -> bool {
+        self.is_redacted_cookie(name)
+            || DEFAULT_HEADERS
+                .iter()
+                .copied()
+                .chain(self.headers.iter().map(String::as_str))
+                .any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    fn is_redacted_cookie(&self, name: &str) -> bool {
+        !self.reveal_cookies && COOKIE_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in headers.iter_mut() {
+            if self.is_redacted(name.as_str()) {
+                *value = HeaderValue::from_static(REDACTED);
+            }
+        }
+    }
+
+    /// フロントエンドに送るイベントのヘッダー。cookie以外はそのまま渡す。
+    pub fn apply_cookies(&self, headers: &mut HeaderMap) {
+        for (name, value) in headers.iter_mut() {
+            if self.is_redacted_cookie(name.as_str()) {
+                *value = HeaderValue::from_static(REDACTED);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redact_default_and_custom_headers() {
+        let redaction = Redaction {
+            headers: vec![String::from("X-Api-Key")],
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+        redaction.apply(&mut headers);
+
+        assert_eq!(headers["authorization"], REDACTED);
+        assert_eq!(headers["x-api-key"], REDACTED);
+        assert_eq!(headers["accept"], "*/*");
+    }
+
+    #[test]
+    fn reveal_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", HeaderValue::from_static("session=secret"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+
+        let mut events = headers.clone();
+        Redaction::default().apply_cookies(&mut events);
+        assert_eq!(events["cookie"], REDACTED);
+        assert_eq!(events["authorization"], "Bearer secret");
+
+        let redaction = Redaction {
+            reveal_cookies: true,
+            ..Default::default()
+        };
+        redaction.apply(&mut headers);
+        assert_eq!(headers["cookie"], "session=secret");
+        assert_eq!(headers["authorization"], REDACTED);
+    }
+}
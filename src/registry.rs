@@ -0,0 +1,23 @@
+use crate::{
+    auth_provider::AuthProvider,
+    body_provider::BodyProvider,
+    body_transform::{RequestTransform, ResponseTransform},
+    fault::Fault,
+    preset::Preset,
+    request_signer::RequestSigner,
+    rewrite::Rewrite,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// 初期化時に`Builder`で登録されたもの
+#[derive(Default)]
+pub struct Registry {
+    pub body_providers: HashMap<String, Arc<dyn BodyProvider>>,
+    pub auth_provider: Option<Arc<dyn AuthProvider>>,
+    pub rewrites: Vec<Rewrite>,
+    pub presets: HashMap<String, Preset>,
+    pub faults: Vec<Fault>,
+    pub request_signers: Vec<(glob::Pattern, Arc<dyn RequestSigner>)>,
+    pub request_transforms: Vec<(glob::Pattern, Arc<dyn RequestTransform>)>,
+    pub response_transforms: Vec<(glob::Pattern, Arc<dyn ResponseTransform>)>,
+}
@@ -0,0 +1,20 @@
+use crate::body_provider::BoxError;
+use reqwest::{header::HeaderMap, Method, Url};
+
+/// 署名に使うリクエストの内容
+#[derive(Debug)]
+pub struct SigningRequest<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub headers: &'a HeaderMap,
+    /// ボディのSHA-256。ストリームで送るボディでは`None`になる。
+    pub body_sha256: Option<[u8; 32]>,
+}
+
+/// 送信の直前にリクエストに署名する。`Builder::request_signer`でホストのパターンと共に登録する。
+/// 再送、認証情報を付けた再送とinteractiveなリダイレクトでは、送るたびに署名し直す。
+#[async_trait::async_trait]
+pub trait RequestSigner: Send + Sync {
+    /// 返したヘッダーはリクエストのヘッダーを置き換える。
+    async fn sign(&self, request: &SigningRequest<'_>) -> Result<HeaderMap, BoxError>;
+}
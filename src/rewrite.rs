@@ -0,0 +1,62 @@
+use crate::cookie_fetch::FetchError;
+
+/// 送信前にURLの先頭`from`を`to`に置き換える。
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+}
+
+/// 最初にマッチした規則のみ適用する。
+pub fn apply(rewrites: &[Rewrite], url: reqwest::Url) -> Result<reqwest::Url, FetchError> {
+    let Some((rewrite, rest)) = rewrites.iter().find_map(|r| {
+        url.as_str()
+            .strip_prefix(r.from.as_str())
+            .map(|rest| (r, rest))
+    }) else {
+        return Ok(url);
+    };
+
+    reqwest::Url::parse(&format!("{}{}", rewrite.to, rest)).map_err(|_| FetchError::InvalidUrl)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rewrites() -> Vec<Rewrite> {
+        vec![
+            Rewrite {
+                from: "https://api.example.com/".to_string(),
+                to: "http://localhost:8080/".to_string(),
+            },
+            Rewrite {
+                from: "https://api.example.com/v2/".to_string(),
+                to: "https://staging.example.com/v2/".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn rewrite_prefix() {
+        let url = reqwest::Url::parse("https://api.example.com/users?id=1").unwrap();
+        let url = apply(&rewrites(), url).unwrap();
+        assert_eq!(url.as_str(), "http://localhost:8080/users?id=1");
+    }
+
+    #[test]
+    fn first_rule_wins() {
+        let url = reqwest::Url::parse("https://api.example.com/v2/users").unwrap();
+        let url = apply(&rewrites(), url).unwrap();
+        assert_eq!(url.as_str(), "http://localhost:8080/v2/users");
+    }
+
+    #[test]
+    fn no_match() {
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            apply(&rewrites(), url).unwrap().as_str(),
+            "https://example.com/"
+        );
+    }
+}
@@ -1,17 +1,62 @@
+use crate::cookie_fetch::Consent;
+use std::{collections::HashMap, sync::Arc};
+
+/// リクエストを許可するURL。`commands`にあるコマンドでは、既定の代わりにそのパターンを使う。
+/// 最初のURLとリダイレクトのたびの遷移先を検査する。
 #[derive(Debug, serde::Deserialize, Default)]
 pub struct Scope {
+    #[serde(flatten)]
+    pub default: UrlPatterns,
+    /// キーはコマンド名。`fetch_batch`の各リクエストのように、内部で送るリクエストにも呼び出したコマンドのものを使う。
+    #[serde(default)]
+    pub commands: HashMap<String, UrlPatterns>,
+}
+
+impl Scope {
+    pub fn for_command(&self, command: &str) -> &UrlPatterns {
+        self.commands.get(command).unwrap_or(&self.default)
+    }
+}
+
+/// どちらかに一致すれば許可する。
+#[derive(Debug, Clone, serde::Deserialize, Default)]
+pub struct UrlPatterns {
     #[serde(deserialize_with = "deserialize_patterns")]
     #[serde(default)]
     pub allowlist: Vec<glob::Pattern>,
+    #[serde(deserialize_with = "deserialize_regexes")]
+    #[serde(default)]
+    pub regex: Vec<regex::Regex>,
 }
 
-impl Scope {
+impl UrlPatterns {
     pub fn is_allowed(&self, url: &reqwest::Url) -> bool {
         self.allowlist.iter().any(|pat| pat.matches(url.as_str()))
+            || self.regex.iter().any(|re| re.is_match(url.as_str()))
     }
 }
 
-fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<glob::Pattern>, D::Error>
+/// リダイレクトの遷移先を検査するため、clientに持たせるscope
+#[derive(Debug, Clone)]
+pub struct ScopeCheck {
+    patterns: UrlPatterns,
+    consent: Arc<Consent>,
+}
+
+impl ScopeCheck {
+    pub fn new(patterns: &UrlPatterns, consent: &Arc<Consent>) -> Self {
+        Self {
+            patterns: patterns.clone(),
+            consent: Arc::clone(consent),
+        }
+    }
+
+    pub fn is_allowed(&self, url: &reqwest::Url) -> bool {
+        self.patterns.is_allowed(url) || self.consent.is_granted(url)
+    }
+}
+
+pub fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<glob::Pattern>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -45,3 +90,44 @@ where
 
     deserializer.deserialize_seq(Visitor)
 }
+
+pub fn deserialize_regexes<'de, D>(deserializer: D) -> Result<Vec<regex::Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let patterns = <Vec<String> as serde::Deserialize>::deserialize(deserializer)?;
+    patterns
+        .iter()
+        .map(|p| regex::Regex::new(p).map_err(<D::Error as serde::de::Error>::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn command_scopes_replace_default() {
+        let scope: Scope = serde_json::from_value(serde_json::json!({
+            "allowlist": ["https://api.example.com/*"],
+            "commands": {
+                "fetch_begin": { "regex": ["^https://cdn\\.example\\.com/"] },
+            },
+        }))
+        .unwrap();
+        let url = |s| reqwest::Url::parse(s).unwrap();
+
+        assert!(scope
+            .for_command("fetch")
+            .is_allowed(&url("https://api.example.com/a")));
+        assert!(!scope
+            .for_command("fetch")
+            .is_allowed(&url("https://cdn.example.com/a")));
+        assert!(scope
+            .for_command("fetch_begin")
+            .is_allowed(&url("https://cdn.example.com/a")));
+        assert!(!scope
+            .for_command("fetch_begin")
+            .is_allowed(&url("https://api.example.com/a")));
+    }
+}
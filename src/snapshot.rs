@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
+
+/// ファイルの内容を丸ごと置き換えて保存する。書き込みはバックグラウンドのタスクで行い、
+/// 追いつかない間に保存された途中の内容は飛ばして最新のものだけを書く。
+#[derive(Debug)]
+pub struct SnapshotFile {
+    tx: Option<watch::Sender<Option<Vec<u8>>>>,
+}
+
+impl SnapshotFile {
+    /// `path`が`None`の場合は何も書き出さない。
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let Some(path) = path else {
+            return Self { tx: None };
+        };
+        let (tx, mut rx) = watch::channel(None::<Vec<u8>>);
+        tauri::async_runtime::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let Some(bytes) = rx.borrow_and_update().clone() else {
+                    continue;
+                };
+                write(&path, bytes).await;
+            }
+        });
+
+        Self { tx: Some(tx) }
+    }
+
+    pub fn save(&self, bytes: Vec<u8>) {
+        if let Some(tx) = &self.tx {
+            tx.send_replace(Some(bytes));
+        }
+    }
+}
+
+/// 書き込みに失敗しても無視する。途中で終了しても壊れないよう、一時ファイルから置き換える。
+async fn write(path: &Path, bytes: Vec<u8>) {
+    if let Some(dir) = path.parent() {
+        let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    let tmp = temporary(path);
+    if tokio::fs::write(&tmp, bytes).await.is_ok() {
+        let _ = tokio::fs::rename(&tmp, path).await;
+    }
+}
+
+fn temporary(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,31 @@
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+
+pub struct Sockets(Mutex<HashMap<String, UnboundedSender<Message>>>);
+
+impl Sockets {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    pub fn insert(&self, channel: String, sender: UnboundedSender<Message>) {
+        self.0.lock().unwrap().insert(channel, sender);
+    }
+
+    /// 同じチャンネルで接続し直されている場合は、新しいソケットを残す。
+    pub fn remove(&self, channel: &str, sender: &UnboundedSender<Message>) {
+        let mut sockets = self.0.lock().unwrap();
+        if sockets.get(channel).is_some_and(|s| s.same_channel(sender)) {
+            sockets.remove(channel);
+        }
+    }
+
+    /// 対応するソケットがない場合、または既に閉じている場合は`false`を返す。
+    pub fn send(&self, channel: &str, message: Message) -> bool {
+        match self.0.lock().unwrap().get(channel) {
+            Some(sender) => sender.send(message).is_ok(),
+            None => false,
+        }
+    }
+}
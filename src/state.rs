@@ -1,6 +1,43 @@
-use crate::CookieClientPool;
+use crate::{
+    aborts::Aborts,
+    cookie_fetch::{
+        AuthReply, Consent, InterceptReply, MemoryCache, Outbox, RedirectReply, RuntimeConfig,
+    },
+    cookie_journal::CookieJournal,
+    logger::Logger,
+    network::NetworkStatus,
+    pending::Pending,
+    pool_stats::PoolStats,
+    readers::Readers,
+    registry::Registry,
+    sockets::Sockets,
+    subscriptions::Subscriptions,
+    throttle::Bandwidth,
+    uploads::Uploads,
+    CookieClientPool,
+};
+use std::sync::Arc;
 
 pub struct CookieFetchState {
     pub client_pool: CookieClientPool,
+    pub subscriptions: Subscriptions,
+    pub sockets: Sockets,
+    pub readers: Readers,
+    pub registry: Registry,
+    pub logger: Logger,
+    pub interceptors: Pending<InterceptReply>,
+    pub redirects: Pending<RedirectReply>,
+    pub auth_challenges: Pending<AuthReply>,
+    pub uploads: Uploads,
+    pub network: NetworkStatus,
+    pub pool_stats: PoolStats,
+    pub aborts: Aborts,
+    /// 全てのリクエストで分け合う帯域の上限
+    pub bandwidth: Bandwidth,
+    pub outbox: Outbox,
+    pub memory_cache: MemoryCache,
+    pub cookie_journal: Option<Arc<CookieJournal>>,
+    pub consent: Arc<Consent>,
+    pub runtime_config: RuntimeConfig,
     pub config: crate::config::Config,
 }
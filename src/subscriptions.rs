@@ -0,0 +1,101 @@
+use crate::CookieFetchState;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tauri::{async_runtime::JoinHandle, Manager};
+
+struct Subscription {
+    /// 同じチャンネルで起動し直した後に、前のタスクが新しい購読を消さないようにする。
+    id: u64,
+    handle: JoinHandle<()>,
+}
+
+pub struct Subscriptions {
+    next_id: AtomicU64,
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `spawn`は割り当てたidを受け取ってタスクを起動する。登録を終えるまでロックを持つため、
+    /// すぐに終わったタスクの`finish`も登録の後に行われる。
+    fn start(&self, channel: String, spawn: impl FnOnce(u64) -> JoinHandle<()>) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let handle = spawn(id);
+        if let Some(previous) = subscriptions.insert(channel, Subscription { id, handle }) {
+            previous.handle.abort();
+        }
+    }
+
+    pub fn unsubscribe(&self, channel: &str) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        match subscriptions.remove(channel) {
+            Some(subscription) => {
+                subscription.handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// タスクの終了時に呼ぶ。abortはしない。`id`が違う場合は起動し直されているため何もしない。
+    fn finish(&self, channel: &str, id: u64) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if subscriptions.get(channel).is_some_and(|s| s.id == id) {
+            subscriptions.remove(channel);
+        }
+    }
+}
+
+/// `task`を`channel`の購読として起動する。
+pub fn spawn<R, F>(app: &tauri::AppHandle<R>, channel: String, task: F)
+where
+    R: tauri::Runtime,
+    F: Future<Output = ()> + Send + 'static,
+{
+    app.state::<CookieFetchState>()
+        .subscriptions
+        .start(channel.clone(), |id| {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                task.await;
+                app.state::<CookieFetchState>()
+                    .subscriptions
+                    .finish(&channel, id);
+            })
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn previous_task_does_not_remove_restarted_subscription() {
+        let subscriptions = Subscriptions::new();
+        let pending = || tauri::async_runtime::spawn(std::future::pending::<()>());
+        subscriptions.start("a".to_string(), |_| pending());
+        subscriptions.start("a".to_string(), |_| pending());
+
+        subscriptions.finish("a", 0);
+        assert!(subscriptions
+            .subscriptions
+            .lock()
+            .unwrap()
+            .contains_key("a"));
+        subscriptions.finish("a", 1);
+        assert!(!subscriptions.unsubscribe("a"));
+    }
+}
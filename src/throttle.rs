@@ -0,0 +1,136 @@
+use crate::{
+    body_provider::{BodyStream, BoxError},
+    body_transform::ResponseStream,
+};
+use bytes::Bytes;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+
+/// 帯域の上限。0または省略した場合は制限しない。
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthConfig {
+    #[serde(default)]
+    pub upload_bytes_per_sec: Option<u64>,
+    #[serde(default)]
+    pub download_bytes_per_sec: Option<u64>,
+}
+
+/// 同じ`Throttle`を使うストリームは帯域を分け合う。
+struct Throttle {
+    bytes_per_sec: u64,
+    /// 次のchunkを流してよい時刻
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    fn new(bytes_per_sec: Option<u64>) -> Option<Arc<Self>> {
+        bytes_per_sec.filter(|r| *r > 0).map(|bytes_per_sec| {
+            Arc::new(Self {
+                bytes_per_sec,
+                next: Mutex::new(Instant::now()),
+            })
+        })
+    }
+
+    /// `len`バイトを流してよい時刻を予約する。
+    fn reserve(&self, len: usize) -> Instant {
+        let mut next = self.next.lock().unwrap();
+        let start = (*next).max(Instant::now());
+        *next = start + Duration::from_secs_f64(len as f64 / self.bytes_per_sec as f64);
+        start
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Bandwidth {
+    upload: Option<Arc<Throttle>>,
+    download: Option<Arc<Throttle>>,
+}
+
+impl Bandwidth {
+    pub fn new(config: &BandwidthConfig) -> Self {
+        Self {
+            upload: Throttle::new(config.upload_bytes_per_sec),
+            download: Throttle::new(config.download_bytes_per_sec),
+        }
+    }
+
+    /// `request`はこのリクエストだけの上限。`self`の上限と合わせて適用する。
+    pub fn is_upload_limited(&self, request: &Bandwidth) -> bool {
+        self.upload.is_some() || request.upload.is_some()
+    }
+
+    pub fn upload(&self, request: &Bandwidth, body: BodyStream) -> BodyStream {
+        match throttles(&self.upload, &request.upload) {
+            Some(throttles) => Box::pin(Throttled::new(body, throttles)),
+            None => body,
+        }
+    }
+
+    pub fn download(&self, request: &Bandwidth, body: ResponseStream) -> ResponseStream {
+        match throttles(&self.download, &request.download) {
+            Some(throttles) => Box::pin(Throttled::new(body, throttles)),
+            None => body,
+        }
+    }
+}
+
+fn throttles(a: &Option<Arc<Throttle>>, b: &Option<Arc<Throttle>>) -> Option<Vec<Arc<Throttle>>> {
+    let throttles: Vec<Arc<Throttle>> = a.iter().chain(b.iter()).cloned().collect();
+    (!throttles.is_empty()).then_some(throttles)
+}
+
+/// chunkを受け取ってから、予約した時刻まで待って返す。
+struct Throttled<S> {
+    inner: S,
+    throttles: Vec<Arc<Throttle>>,
+    delayed: Option<(Pin<Box<Sleep>>, Bytes)>,
+}
+
+impl<S> Throttled<S> {
+    fn new(inner: S, throttles: Vec<Arc<Throttle>>) -> Self {
+        Self {
+            inner,
+            throttles,
+            delayed: None,
+        }
+    }
+}
+
+impl<S> futures_util::Stream for Throttled<S>
+where
+    S: futures_util::Stream<Item = Result<Bytes, BoxError>> + Unpin,
+{
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((sleep, _)) = &mut this.delayed {
+                return match sleep.as_mut().poll(cx) {
+                    Poll::Ready(()) => Poll::Ready(this.delayed.take().map(|(_, chunk)| Ok(chunk))),
+                    Poll::Pending => Poll::Pending,
+                };
+            }
+
+            let chunk = match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => chunk,
+                poll => return poll,
+            };
+            let at = this.throttles.iter().map(|t| t.reserve(chunk.len())).max();
+            match at {
+                Some(at) if at > Instant::now() => {
+                    this.delayed = Some((Box::pin(tokio::time::sleep_until(at)), chunk));
+                }
+                _ => return Poll::Ready(Some(Ok(chunk))),
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::body_provider::BoxError;
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// チャンクの送信側が先行しすぎないようにする
+const UPLOAD_BUFFER: usize = 4;
+
+/// JS側から`upload_chunk`で少しずつ送られるリクエストボディ
+pub struct Uploads {
+    next_id: AtomicU64,
+    senders: Mutex<HashMap<u64, mpsc::Sender<Result<Bytes, BoxError>>>>,
+    receivers: Mutex<HashMap<u64, mpsc::Receiver<Result<Bytes, BoxError>>>>,
+}
+
+impl Uploads {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            senders: Mutex::new(HashMap::new()),
+            receivers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn begin(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(UPLOAD_BUFFER);
+        self.senders.lock().unwrap().insert(id, tx);
+        self.receivers.lock().unwrap().insert(id, rx);
+        id
+    }
+
+    pub fn sender(&self, id: u64) -> Option<mpsc::Sender<Result<Bytes, BoxError>>> {
+        self.senders.lock().unwrap().get(&id).cloned()
+    }
+
+    /// 送信側を閉じる。送信済みのチャンクはそのままリクエストで読まれる。
+    pub fn end(&self, id: u64) {
+        self.senders.lock().unwrap().remove(&id);
+    }
+
+    pub fn cancel(&self, id: u64) {
+        self.senders.lock().unwrap().remove(&id);
+        self.receivers.lock().unwrap().remove(&id);
+    }
+
+    pub fn take(&self, id: u64) -> Option<UploadStream> {
+        self.receivers.lock().unwrap().remove(&id).map(UploadStream)
+    }
+}
+
+pub struct UploadStream(mpsc::Receiver<Result<Bytes, BoxError>>);
+
+impl futures_util::Stream for UploadStream {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}